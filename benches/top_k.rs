@@ -0,0 +1,117 @@
+//! Compares ranked suggestions served from the top-k node caches against a
+//! full subtree scan, both in the trie and through `Autocomplete` with its
+//! default ranker. Run with `cargo bench --bench top_k`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use arbor_cli::common::autocomplete::Autocomplete;
+use arbor_cli::common::ranking;
+use arbor_cli::common::trie::Trie;
+
+const LIMIT: usize = 10;
+const QUERIES: usize = 200;
+
+fn main() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    for size in [1_000, 10_000, 100_000] {
+        let words = generate_words(size);
+
+        let mut cached = Trie::with_top_k(LIMIT);
+        let mut scanned = Trie::new();
+
+        for (i, word) in words.iter().enumerate() {
+            // NOTE: give words different frequencies so ranking matters
+            for _ in 0..=(i % 5) {
+                cached.insert(word).unwrap();
+                scanned.insert(word).unwrap();
+            }
+        }
+
+        let cached_time = measure(&cached);
+        let scanned_time = measure(&scanned);
+
+        println!(
+            "{:>7} words: top-k cache {:>10.2?}/query, scan {:>10.2?}/query",
+            size,
+            cached_time / QUERIES as u32,
+            scanned_time / QUERIES as u32,
+        );
+
+        let (default_time, unbounded_time) = runtime.block_on(measure_suggestions(&words));
+
+        println!(
+            "{:>7} words: suggest_word {:>10.2?}/query, without a bound {:>10.2?}/query",
+            size,
+            default_time / QUERIES as u32,
+            unbounded_time / QUERIES as u32,
+        );
+    }
+}
+
+/// Times `suggest_word` with the default ranker, which stops at the cached
+/// words, and with a ranker that has no bound and scores the whole subtree.
+async fn measure_suggestions(words: &[String]) -> (Duration, Duration) {
+    let mut autocomplete = Autocomplete::build(None, None, Some(LIMIT as u8), false, None)
+        .await
+        .unwrap();
+
+    for (i, word) in words.iter().enumerate() {
+        autocomplete
+            .insert_words(vec![word.clone(); i % 5 + 1])
+            .await
+            .unwrap();
+    }
+
+    let default_time = measure_autocomplete(&autocomplete).await;
+
+    autocomplete.set_ranker(ranking::parse("lexicographic").unwrap());
+
+    let unbounded_time = measure_autocomplete(&autocomplete).await;
+
+    (default_time, unbounded_time)
+}
+
+async fn measure_autocomplete(autocomplete: &Autocomplete) -> Duration {
+    let start = Instant::now();
+
+    for i in 0..QUERIES {
+        let prefix = ((b'a' + (i % 26) as u8) as char).to_string();
+
+        black_box(autocomplete.suggest_word(&prefix).await.unwrap());
+    }
+
+    start.elapsed()
+}
+
+fn measure(trie: &Trie) -> Duration {
+    let start = Instant::now();
+
+    for i in 0..QUERIES {
+        let prefix = ((b'a' + (i % 26) as u8) as char).to_string();
+
+        black_box(trie.top(&prefix, LIMIT));
+    }
+
+    start.elapsed()
+}
+
+/// Deterministic pseudo-random lowercase words of 3 to 10 letters.
+fn generate_words(count: usize) -> Vec<String> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            let length = 3 + (state % 8) as usize;
+
+            (0..length)
+                .map(|i| (b'a' + ((state >> (i * 5)) % 26) as u8) as char)
+                .collect()
+        })
+        .collect()
+}
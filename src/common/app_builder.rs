@@ -1,12 +1,48 @@
-use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use super::autocomplete::Autocomplete;
+use tokio::io::AsyncReadExt;
+
+use crate::util::{
+    app_data::app_data_dir,
+    backup::DEFAULT_SEGMENT_SIZE,
+    compression::Compression,
+    encryption::KeySource,
+    errors::{ArborError, ConfigError, StorageError},
+    keymap::Keymap,
+    theme::Theme,
+};
+
+use super::autocomplete::{Autocomplete, Entry};
+use super::dictionary::{Dictionary, Layer};
+use super::filter::WordFilter;
+use super::infix::DEFAULT_BUDGET;
+use super::pattern::{PatternLimits, DEFAULT_MAX_RESULTS, DEFAULT_MAX_VISITED};
+use super::phonetic::PhoneticAlgorithm;
+use super::ranking;
+use super::shortcodes::{ShortcodeSet, Shortcodes};
+use super::snippets::Snippets;
+use super::spellcheck::{self, DEFAULT_MAX_DISTANCE};
+use super::stemmer::{self, Stemmer};
+use super::thesaurus::Thesaurus;
+use super::tokenizer::{Tokenizer, TokenizerRules};
+
+const KEYMAP_FILE_NAME: &str = "keymap";
+const HIDDEN_FILE_NAME: &str = "hidden";
+const RANKER_FILE_NAME: &str = "ranker";
+const SNIPPETS_FILE_NAME: &str = "snippets";
+const BLOCKLIST_FILE_NAME: &str = "blocklist";
+const COMPRESSION_FILE_NAME: &str = "compression";
+const TOKENIZER_FILE_NAME: &str = "tokenizer";
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(short, long)]
     language: Option<String>,
 
@@ -21,36 +57,628 @@ pub struct Args {
 
     #[arg(short, long, requires("backup"))]
     output: Option<String>,
+
+    #[arg(short, long, value_parser = ["emacs", "vi"])]
+    keymap: Option<String>,
+
+    #[arg(long, value_parser = ["default", "no-color", "high-contrast"])]
+    theme: Option<String>,
+
+    #[arg(long)]
+    prompt: Option<String>,
+
+    #[arg(long)]
+    normal_prompt: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    show_score: bool,
+
+    #[arg(long, default_value_t = false)]
+    learn_paste: bool,
+
+    #[arg(short, long)]
+    entries: Option<String>,
+
+    /// Read-only dictionary layered under learned words; may be repeated.
+    #[arg(short, long)]
+    dictionary: Vec<String>,
+
+    /// Team-shared dictionary, ranked above the system ones; may be repeated.
+    #[arg(long)]
+    team_dictionary: Vec<String>,
+
+    /// Scales a layer's scores, e.g. `team=2` or `system=0.5`.
+    #[arg(long, value_parser = parse_layer_weight)]
+    layer_weight: Vec<(Layer, f64)>,
+
+    /// Days after which an unused word counts half as much when ranking.
+    #[arg(long, value_parser = parse_days)]
+    half_life: Option<Duration>,
+
+    /// How suggestions are ordered, e.g. `frequency` or `frequency-recency=1,context=2`.
+    #[arg(short, long)]
+    ranker: Option<String>,
+
+    /// File of tokenizer rules, e.g. `apostrophes = split`, optionally under
+    /// `[language]` headers; defaults to the tokenizer file of the app data.
+    #[arg(long)]
+    tokenizer_rules: Option<String>,
+
+    /// Builds an index for finding words by a fragment from their middle or end.
+    #[arg(long, default_value_t = false)]
+    infix_index: bool,
+
+    /// Memory budget for the infix index, in megabytes.
+    #[arg(long, requires("infix_index"))]
+    infix_budget: Option<usize>,
+
+    /// Thesaurus file offering synonyms and related words for complete words.
+    #[arg(long)]
+    thesaurus: Option<String>,
+
+    /// Completes shortcodes like `:smile`, `\alpha` or `&amp` to their glyphs.
+    #[arg(long, default_value_t = false)]
+    shortcodes: bool,
+
+    /// Changes the sigil a shortcode set is typed with, e.g. `latex=@`.
+    #[arg(long, value_parser = parse_sigil, requires("shortcodes"))]
+    shortcode_sigil: Vec<(ShortcodeSet, char)>,
+
+    /// Also suggests words that sound alike; defaults to the language's algorithm.
+    #[arg(long, value_parser = ["double-metaphone", "soundex", "off"])]
+    phonetic: Option<String>,
+
+    /// Counts inflections such as `runs` and `running` together under their stem.
+    #[arg(long, default_value_t = false)]
+    stemming: bool,
+
+    /// Never learns nor suggests the language's common stop words, e.g. `the`.
+    #[arg(long, default_value_t = false)]
+    stop_words: bool,
+
+    /// File of words and `/regex/` rules never learned nor suggested; may be repeated.
+    #[arg(long)]
+    blocklist: Vec<String>,
+
+    /// Learns words for this session only, without writing them to the backup.
+    #[arg(long, default_value_t = false)]
+    incognito: bool,
+
+    /// Encrypts the backup with the key in this file, 32 bytes or 64 hex digits.
+    #[arg(long, requires("backup"), conflicts_with("passphrase_env"))]
+    key_file: Option<String>,
+
+    /// Encrypts the backup with a key derived from the passphrase in this environment variable.
+    #[arg(long, requires("backup"))]
+    passphrase_env: Option<String>,
+
+    /// Compresses backup segments and compiled dictionaries: `none`, `gzip` or `zstd`.
+    #[arg(long, value_parser = ["none", "gzip", "zstd"])]
+    compression: Option<String>,
+
+    /// Size in megabytes past which the backup is moved into a compressed segment.
+    #[arg(long, requires("backup"))]
+    segment_size: Option<u64>,
+}
+
+fn parse_days(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|days| Duration::try_from_secs_f64(days * 24.0 * 60.0 * 60.0).ok())
+        .ok_or_else(|| format!("invalid number of days \"{}\"", value))
+}
+
+fn parse_layer_weight(value: &str) -> Result<(Layer, f64), String> {
+    let (layer, weight) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected LAYER=WEIGHT, got \"{}\"", value))?;
+    let layer = Layer::parse(layer).ok_or_else(|| format!("unknown layer \"{}\"", layer))?;
+    let weight = weight
+        .parse::<f64>()
+        .ok()
+        .filter(|weight| *weight >= 0.0)
+        .ok_or_else(|| format!("invalid weight \"{}\"", weight))?;
+
+    Ok((layer, weight))
+}
+
+fn parse_sigil(value: &str) -> Result<(ShortcodeSet, char), String> {
+    let (set, sigil) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected SET=SIGIL, got \"{}\"", value))?;
+    let set =
+        ShortcodeSet::parse(set).ok_or_else(|| format!("unknown shortcode set \"{}\"", set))?;
+
+    let mut chars = sigil.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(sigil), None) if !sigil.is_alphanumeric() && !sigil.is_whitespace() => {
+            Ok((set, sigil))
+        }
+        _ => Err(format!("invalid sigil \"{}\"", sigil)),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compiles a word list into a read-only dictionary file.
+    CompileDictionary { word_list: String, output: String },
+    /// Prints the words matching a pattern such as `h?ll*` or `c[ao]t`.
+    Match {
+        pattern: String,
+
+        /// Stops after this many matches.
+        #[arg(long, default_value_t = DEFAULT_MAX_RESULTS)]
+        limit: usize,
+
+        /// Stops after visiting this many trie nodes.
+        #[arg(long, default_value_t = DEFAULT_MAX_VISITED)]
+        max_visited: usize,
+    },
+    /// Prints the completions of a prefix, or the words related to a word.
+    Query {
+        prefix: String,
+
+        /// Prints `relation<TAB>word` lines from the thesaurus instead of completions.
+        #[arg(long, default_value_t = false)]
+        related: bool,
+    },
+    /// Prints each stem with its combined count and the learned words sharing it.
+    Stems,
+    /// Forgets the learned words matching a pattern such as `pass*`, backup included.
+    Purge { pattern: String },
+    /// Re-encrypts the backup with a new key, or encrypts a plain one.
+    RotateKey {
+        /// File holding the new key.
+        #[arg(
+            long,
+            conflicts_with("new_passphrase_env"),
+            required_unless_present("new_passphrase_env")
+        )]
+        new_key_file: Option<String>,
+
+        /// Environment variable holding the new passphrase.
+        #[arg(long)]
+        new_passphrase_env: Option<String>,
+    },
+    /// Adds the `trigger<TAB>expansion` lines of a file to the saved snippets.
+    ImportSnippets { file: String },
+    /// Flags the words in a file, or standard input, that aren't known.
+    Check {
+        file: Option<String>,
+
+        /// Prints a JSON array with byte offsets instead of one line per word.
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        /// Proposes corrections up to this many edits away.
+        #[arg(long, default_value_t = DEFAULT_MAX_DISTANCE)]
+        max_distance: usize,
+    },
+}
+
+impl Command {
+    /// Runs the command without the interactive prompt, using the rest of
+    /// `args` to set up the dictionaries and learned words it needs.
+    pub async fn run(&self, args: Args) -> Result<(), ArborError> {
+        match self {
+            Command::CompileDictionary { word_list, output } => {
+                let count = Dictionary::compile_with(
+                    Path::new(word_list),
+                    Path::new(output),
+                    compression_for(&args)?,
+                )?;
+
+                println!("Compiled {} words into {}.", count, output);
+            }
+            Command::Match {
+                pattern,
+                limit,
+                max_visited,
+            } => {
+                let mut arbor = Arbor::build_with_args(args).await?;
+
+                arbor.autocomplete.load_backup().await?;
+                arbor.autocomplete.set_pattern_limits(PatternLimits {
+                    max_results: *limit,
+                    max_visited: *max_visited,
+                });
+
+                let matches = arbor.autocomplete.match_pattern(pattern).await?;
+
+                for suggestion in &matches.suggestions {
+                    println!("{}", suggestion.word);
+                }
+
+                if matches.truncated {
+                    eprintln!("arbor: stopped early, more words may match.");
+                }
+            }
+            Command::Query { prefix, related } => {
+                let mut arbor = Arbor::build_with_args(args).await?;
+
+                if *related && arbor.autocomplete.get_thesaurus().is_none() {
+                    return Err(ConfigError::MissingThesaurus.into());
+                }
+
+                arbor.autocomplete.load_backup().await?;
+
+                let completions = arbor
+                    .autocomplete
+                    .suggest_with_related(None, prefix)
+                    .await?;
+
+                if *related {
+                    for related in &completions.related {
+                        println!("{}\t{}", related.relation, related.word);
+                    }
+                } else {
+                    for suggestion in &completions.suggestions {
+                        println!("{}", suggestion.word);
+                    }
+                }
+            }
+            Command::Stems => {
+                let mut arbor = Arbor::build_with_args(args).await?;
+                let autocomplete = &mut arbor.autocomplete;
+
+                if autocomplete.get_stem_index().is_none() {
+                    autocomplete.set_stemmer(Some(stemmer_for(autocomplete)?));
+                }
+
+                autocomplete.load_backup().await?;
+
+                for (stem, count, words) in autocomplete.stem_counts() {
+                    println!("{}\t{}\t{}", stem, count, words.join(", "));
+                }
+            }
+            Command::Purge { pattern } => {
+                let mut arbor = Arbor::build_with_args(args).await?;
+                let purged = arbor.autocomplete.purge(pattern).await?;
+
+                println!("Purged {} words.", purged.len());
+            }
+            Command::RotateKey {
+                new_key_file,
+                new_passphrase_env,
+            } => {
+                let source = key_source(new_key_file.as_deref(), new_passphrase_env.as_deref())?
+                    .ok_or(ConfigError::MissingNewKey)?;
+                let mut arbor = Arbor::build_with_args(args).await?;
+
+                if arbor.autocomplete.get_backup().is_none() {
+                    return Err(ConfigError::MissingBackup.into());
+                }
+
+                arbor.autocomplete.rotate_backup_key(&source).await?;
+
+                println!("Re-encrypted the backup with the new key.");
+            }
+            Command::ImportSnippets { file } => {
+                // NOTE: snippets don't need the backup, so it's never unlocked
+                let mut snippets =
+                    Snippets::load(&app_data_dir()?.join(SNIPPETS_FILE_NAME)).await?;
+                let count = snippets.import(Path::new(file)).await?;
+
+                println!("Imported {} snippets.", count);
+            }
+            Command::Check {
+                file,
+                json,
+                max_distance,
+            } => {
+                let text = read_input(file.as_deref()).await?;
+                let mut arbor = Arbor::build_with_args(args).await?;
+
+                arbor.autocomplete.load_backup().await?;
+
+                let misspellings = arbor.autocomplete.check_text(&text, *max_distance).await;
+
+                if *json {
+                    println!("{}", spellcheck::to_json(&misspellings));
+                    return Ok(());
+                }
+
+                let name = file.as_deref().unwrap_or("<stdin>");
+
+                for misspelling in &misspellings {
+                    let (line, column) = misspelling.position(&text);
+                    let corrections = misspelling
+                        .corrections
+                        .iter()
+                        .map(|correction| correction.word.as_str())
+                        .collect::<Vec<&str>>();
+
+                    if corrections.is_empty() {
+                        println!(
+                            "{}:{}:{}: unknown word \"{}\"",
+                            name, line, column, misspelling.word
+                        );
+                    } else {
+                        println!(
+                            "{}:{}:{}: unknown word \"{}\", did you mean: {}",
+                            name,
+                            line,
+                            column,
+                            misspelling.word,
+                            corrections.join(", ")
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads `file`, or standard input when there is none.
+async fn read_input(file: Option<&str>) -> Result<String, ArborError> {
+    let path = PathBuf::from(file.unwrap_or("-"));
+    let read_error = |source| StorageError::Read {
+        path: path.clone(),
+        source,
+    };
+
+    let text = match file {
+        Some(file) => tokio::fs::read_to_string(file).await.map_err(read_error)?,
+        None => {
+            let mut text = String::new();
+
+            tokio::io::stdin()
+                .read_to_string(&mut text)
+                .await
+                .map_err(read_error)?;
+
+            text
+        }
+    };
+
+    Ok(text)
+}
+
+/// The compression given with --compression, or else the one in the
+/// compression file of the app data directory.
+fn compression_for(args: &Args) -> Result<Compression, ArborError> {
+    let path = app_data_dir()?.join(COMPRESSION_FILE_NAME);
+    let name = match &args.compression {
+        Some(name) => name.clone(),
+        None if path.exists() => {
+            std::fs::read_to_string(&path).map_err(|source| ConfigError::Read {
+                path: path.clone(),
+                source,
+            })?
+        }
+        None => return Ok(Compression::None),
+    };
+
+    match Compression::parse(&name) {
+        Some(compression) => Ok(compression),
+        None => Err(ConfigError::UnknownCompression(name).into()),
+    }
+}
+
+/// The backup key given as a key file or the name of an environment
+/// variable holding a passphrase, if either is.
+fn key_source(
+    key_file: Option<&str>,
+    passphrase_env: Option<&str>,
+) -> Result<Option<KeySource>, ConfigError> {
+    if let Some(key_file) = key_file {
+        return Ok(Some(KeySource::File(PathBuf::from(key_file))));
+    }
+
+    let Some(variable) = passphrase_env else {
+        return Ok(None);
+    };
+
+    match std::env::var(variable) {
+        Ok(passphrase) if !passphrase.is_empty() => Ok(Some(KeySource::Passphrase(passphrase))),
+        _ => Err(ConfigError::MissingPassphrase(variable.to_string())),
+    }
+}
+
+/// The tokenizer for the language, with the rules of the file given with
+/// --tokenizer-rules, or else of the tokenizer file of the app data directory.
+fn tokenizer_for(args: &Args, language: &str) -> Result<Tokenizer, ConfigError> {
+    let path = match &args.tokenizer_rules {
+        Some(path) => PathBuf::from(path),
+        None => app_data_dir()?.join(TOKENIZER_FILE_NAME),
+    };
+
+    if args.tokenizer_rules.is_none() && !path.exists() {
+        return Ok(Tokenizer::for_language(language));
+    }
+
+    let config = std::fs::read_to_string(&path).map_err(|source| ConfigError::Read {
+        path: path.clone(),
+        source,
+    })?;
+
+    Ok(Tokenizer::new(TokenizerRules::with_overrides(
+        language, &config,
+    )?))
+}
+
+fn stemmer_for(autocomplete: &Autocomplete<Entry>) -> Result<Box<dyn Stemmer>, ConfigError> {
+    let language = autocomplete.get_app_data().get_language();
+
+    stemmer::for_language(language).ok_or_else(|| ConfigError::NoStemmer(language.to_string()))
 }
 
 impl Arbor {
-    pub async fn build() -> Result<Arbor, Box<dyn Error>>
+    pub async fn build() -> Result<Arbor, ArborError>
     where
         Self: Sync,
     {
-        let args = Args::parse();
+        Self::build_with_args(Args::parse()).await
+    }
+
+    pub async fn build_with_args(args: Args) -> Result<Arbor, ArborError> {
         let output = args.output.as_deref();
 
+        let mut autocomplete = Autocomplete::build_with_payload(
+            args.language.clone(),
+            args.thread_count,
+            args.max_suggestion,
+            args.backup,
+            output,
+        )
+        .await?;
+
+        if let Some(source) = key_source(args.key_file.as_deref(), args.passphrase_env.as_deref())?
+        {
+            autocomplete.unlock_backup(&source).await?;
+        }
+
+        autocomplete.set_backup_compression(
+            compression_for(&args)?,
+            args.segment_size.map_or(DEFAULT_SEGMENT_SIZE, |megabytes| {
+                megabytes.saturating_mul(1024 * 1024)
+            }),
+        );
+
+        autocomplete.set_tokenizer(tokenizer_for(
+            &args,
+            autocomplete.get_app_data().get_language(),
+        )?);
+
+        let mut filter = WordFilter::new();
+        let blocklist_path = app_data_dir()?.join(BLOCKLIST_FILE_NAME);
+
+        if args.stop_words {
+            filter.add_stop_words(autocomplete.get_app_data().get_language());
+        }
+
+        if blocklist_path.exists() {
+            filter.load(&blocklist_path).await?;
+        }
+
+        for blocklist in &args.blocklist {
+            filter.load(Path::new(blocklist)).await?;
+        }
+
+        autocomplete.set_filter(filter);
+        autocomplete.set_incognito(args.incognito);
+
+        if let Some(entries) = &args.entries {
+            autocomplete.load_entries(Path::new(entries)).await?;
+        }
+
+        for dictionary in &args.dictionary {
+            autocomplete.add_dictionary(Dictionary::open(Path::new(dictionary))?, Layer::System);
+        }
+
+        for dictionary in &args.team_dictionary {
+            autocomplete.add_dictionary(Dictionary::open(Path::new(dictionary))?, Layer::Team);
+        }
+
+        for (layer, weight) in &args.layer_weight {
+            autocomplete.set_layer_weight(*layer, *weight);
+        }
+
+        if let Some(half_life) = args.half_life {
+            autocomplete.set_half_life(half_life);
+        }
+
+        let ranker_path = app_data_dir()?.join(RANKER_FILE_NAME);
+        let ranker = match &args.ranker {
+            Some(ranker) => Some(ranker.clone()),
+            None if ranker_path.exists() => Some(std::fs::read_to_string(&ranker_path).map_err(
+                |source| ConfigError::Read {
+                    path: ranker_path.clone(),
+                    source,
+                },
+            )?),
+            None => None,
+        };
+
+        if let Some(ranker) = ranker {
+            autocomplete.set_ranker(ranking::parse(&ranker)?);
+        }
+
+        autocomplete
+            .load_hidden(&app_data_dir()?.join(HIDDEN_FILE_NAME))
+            .await?;
+
+        if args.infix_index {
+            let budget = args.infix_budget.map_or(DEFAULT_BUDGET, |megabytes| {
+                megabytes.saturating_mul(1024 * 1024)
+            });
+
+            autocomplete.enable_infix_index(budget);
+        }
+
+        if let Some(thesaurus) = &args.thesaurus {
+            autocomplete.set_thesaurus(Thesaurus::load(Path::new(thesaurus)).await?);
+        }
+
+        if let Some(phonetic) = &args.phonetic {
+            autocomplete.set_phonetic(PhoneticAlgorithm::parse(phonetic));
+        }
+
+        if args.stemming {
+            autocomplete.set_stemmer(Some(stemmer_for(&autocomplete)?));
+        }
+
+        let snippets = Snippets::load(&app_data_dir()?.join(SNIPPETS_FILE_NAME)).await?;
+        let shortcodes = args.shortcodes.then(|| {
+            let sets: Vec<(ShortcodeSet, char)> = ShortcodeSet::ALL
+                .iter()
+                .map(|set| {
+                    let sigil = args
+                        .shortcode_sigil
+                        .iter()
+                        .rev()
+                        .find(|(configured, _)| configured == set)
+                        .map_or(set.default_sigil(), |(_, sigil)| *sigil);
+
+                    (*set, sigil)
+                })
+                .collect();
+
+            Shortcodes::new(&sets)
+        });
+
         Ok(Arbor {
-            autocomplete: Autocomplete::build(
-                args.language.clone(),
-                args.thread_count,
-                args.max_suggestion,
-                args.backup,
-                output,
-            )
-            .await?,
+            autocomplete,
+            snippets,
+            shortcodes,
+            keymap: Keymap::build(
+                args.keymap.as_deref(),
+                Some(&app_data_dir()?.join(KEYMAP_FILE_NAME)),
+            )?,
+            theme: Theme::build(
+                args.theme.as_deref(),
+                args.prompt.clone(),
+                args.normal_prompt.clone(),
+                args.show_score,
+            )?,
+            learn_paste: args.learn_paste,
         })
     }
 }
 
 pub struct Arbor {
-    pub autocomplete: Autocomplete,
+    pub autocomplete: Autocomplete<Entry>,
+    pub snippets: Snippets,
+    /// Emoji, LaTeX and HTML shortcodes, when enabled.
+    pub shortcodes: Option<Shortcodes>,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub learn_paste: bool,
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::iter_nth,
+    clippy::iter_nth_zero,
+    clippy::needless_borrows_for_generic_args,
+    clippy::needless_return
+)]
 mod tests {
-    use std::ffi::OsString;
+    use std::{error::Error, ffi::OsString};
 
     use super::*;
 
@@ -63,6 +691,241 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn it_parses_keymap_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--keymap", "vi"])?;
+
+        assert_eq!(args.keymap, Some("vi".to_string()));
+        assert!(get_args(["arbor", "--keymap", "ed"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_dictionary_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "-d", "system.fst", "-d", "team.fst"])?;
+
+        assert_eq!(args.dictionary, vec!["system.fst", "team.fst"]);
+
+        let args = get_args(["arbor", "--layer-weight", "team=2.5"])?;
+
+        assert_eq!(args.layer_weight, vec![(Layer::Team, 2.5)]);
+        assert!(get_args(["arbor", "--layer-weight", "global=1"]).is_err());
+        assert!(get_args(["arbor", "--layer-weight", "team"]).is_err());
+
+        let args = get_args(["arbor", "compile-dictionary", "words.txt", "words.fst"])?;
+
+        assert!(matches!(
+            args.command,
+            Some(Command::CompileDictionary { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_ranking_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--half-life", "0.5"])?;
+
+        assert_eq!(args.half_life, Some(Duration::from_secs(12 * 60 * 60)));
+        assert!(get_args(["arbor", "--half-life", "-1"]).is_err());
+
+        let args = get_args(["arbor", "--ranker", "frequency=1,context=2"])?;
+
+        assert_eq!(args.ranker, Some("frequency=1,context=2".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_tokenizer_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--tokenizer-rules", "tokenizer.conf"])?;
+
+        assert_eq!(args.tokenizer_rules, Some("tokenizer.conf".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_infix_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--infix-index", "--infix-budget", "16"])?;
+
+        assert!(args.infix_index);
+        assert_eq!(args.infix_budget, Some(16));
+        assert!(get_args(["arbor", "--infix-budget", "16"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_shortcode_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--shortcodes", "--shortcode-sigil", "latex=@"])?;
+
+        assert!(args.shortcodes);
+        assert_eq!(args.shortcode_sigil, vec![(ShortcodeSet::Latex, '@')]);
+        assert!(get_args(["arbor", "--shortcodes", "--shortcode-sigil", "latex=a"]).is_err());
+        assert!(get_args(["arbor", "--shortcode-sigil", "emoji=;"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_phonetic_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--phonetic", "soundex"])?;
+
+        assert_eq!(args.phonetic, Some("soundex".to_string()));
+        assert!(get_args(["arbor", "--phonetic", "nysiis"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_match_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args([
+            "arbor",
+            "-d",
+            "words.fst",
+            "match",
+            "c[ao]t",
+            "--limit",
+            "5",
+        ])?;
+
+        assert_eq!(args.dictionary, vec!["words.fst"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Match { limit: 5, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_thesaurus_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args([
+            "arbor",
+            "--thesaurus",
+            "thesaurus.txt",
+            "query",
+            "happy",
+            "--related",
+        ])?;
+
+        assert_eq!(args.thesaurus, Some("thesaurus.txt".to_string()));
+        assert!(matches!(
+            args.command,
+            Some(Command::Query { related: true, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_filter_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args([
+            "arbor",
+            "--stop-words",
+            "--blocklist",
+            "names.txt",
+            "--blocklist",
+            "codenames.txt",
+        ])?;
+
+        assert!(args.stop_words);
+        assert_eq!(args.blocklist, vec!["names.txt", "codenames.txt"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_privacy_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--incognito", "purge", "pass*"])?;
+
+        assert!(args.incognito);
+        assert!(matches!(
+            args.command,
+            Some(Command::Purge { ref pattern }) if pattern == "pass*"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_encryption_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args([
+            "arbor",
+            "--backup",
+            "--passphrase-env",
+            "ARBOR_PASSPHRASE",
+            "rotate-key",
+            "--new-key-file",
+            "backup.key",
+        ])?;
+
+        assert_eq!(args.passphrase_env, Some("ARBOR_PASSPHRASE".to_string()));
+        assert!(matches!(
+            args.command,
+            Some(Command::RotateKey { new_key_file: Some(ref file), .. }) if file == "backup.key"
+        ));
+        assert!(get_args(["arbor", "--key-file", "backup.key"]).is_err());
+        assert!(get_args(["arbor", "--backup", "rotate-key"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_compression_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args([
+            "arbor",
+            "--backup",
+            "--compression",
+            "zstd",
+            "--segment-size",
+            "4",
+        ])?;
+
+        assert_eq!(args.compression, Some("zstd".to_string()));
+        assert_eq!(args.segment_size, Some(4));
+        assert!(get_args(["arbor", "--compression", "lz4"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_stemming_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "--stemming", "stems"])?;
+
+        assert!(args.stemming);
+        assert!(matches!(args.command, Some(Command::Stems)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_snippet_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "import-snippets", "snippets.txt"])?;
+
+        assert!(matches!(args.command, Some(Command::ImportSnippets { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_parses_check_args() -> Result<(), Box<dyn Error>> {
+        let args = get_args(["arbor", "check", "notes.txt", "--json"])?;
+
+        assert!(matches!(
+            args.command,
+            Some(Command::Check {
+                json: true,
+                max_distance: DEFAULT_MAX_DISTANCE,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn it_builds_app() -> Result<(), Box<dyn Error>> {
         let mut arbor = Arbor::build().await?;
@@ -1,13 +1,92 @@
-use std::error::Error;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::util::{app_data::AppData, backup::Backup};
+use tokio::io::AsyncWriteExt;
 
+use crate::util::{
+    app_data::AppData,
+    backup::Backup,
+    compression::Compression,
+    encryption::KeySource,
+    errors::{ArborError, StorageError, TokenizerError},
+};
+
+use super::dictionary::{Dictionary, Layer};
+use super::filter::{FilterStats, WordFilter};
+use super::infix::InfixIndex;
+use super::pattern::{Pattern, PatternLimits};
+use super::phonetic::{PhoneticAlgorithm, PhoneticIndex};
+use super::ranking::{unix_time, CacheOrder, Candidate, Context, FrequencyRecency, Ranker};
+use super::spellcheck::{Correction, Levenshtein, Misspelling};
+use super::stemmer::{StemIndex, Stemmer};
+use super::thesaurus::{RelatedWord, Thesaurus};
+use super::tokenizer::{Rejected, TokenReport, Tokenizer};
 use super::trie::Trie;
 
-pub struct Autocomplete {
+/// Data attached to a completion, e.g. a product SKU or a command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub id: Option<String>,
+}
+
+/// A ranked completion together with the layer it was found in.
+#[derive(Debug, PartialEq)]
+pub struct Suggestion<'a, V> {
+    pub word: String,
+    pub score: u32,
+    pub value: &'a V,
+    pub layer: Layer,
+}
+
+/// Words matching a pattern across every layer, alphabetically.
+#[derive(Debug, PartialEq)]
+pub struct PatternMatches<'a, V> {
+    pub suggestions: Vec<Suggestion<'a, V>>,
+    /// Whether a limit cut the search short, so more words may match.
+    pub truncated: bool,
+}
+
+/// Completions for a prefix together with the words related to it, e.g.
+/// synonyms once the prefix is a whole word.
+#[derive(Debug, PartialEq)]
+pub struct Completions<'a, V> {
+    pub suggestions: Vec<Suggestion<'a, V>>,
+    pub related: Vec<RelatedWord>,
+}
+
+/// Shorter prefixes sound like too many words to be worth suggesting.
+const MIN_PHONETIC_LENGTH: usize = 3;
+
+pub struct Autocomplete<V = ()> {
     app_data: AppData,
     backup: Option<Backup>,
-    trie: Trie,
+    tokenizer: Tokenizer,
+    trie: Trie<V>,
+    dictionaries: Vec<(Layer, Dictionary)>,
+    layer_weights: HashMap<Layer, f64>,
+    /// Dictionary words the user never wants suggested.
+    hidden: HashSet<String>,
+    hidden_path: Option<PathBuf>,
+    ranker: Box<dyn Ranker>,
+    pattern_limits: PatternLimits,
+    /// Optional index for finding words by a fragment from their middle or end.
+    infix: Option<InfixIndex>,
+    /// Words grouped by how they sound, when the language has an algorithm.
+    phonetic: Option<PhoneticIndex>,
+    /// Learned words grouped by stem, when stemming is on.
+    stems: Option<StemIndex>,
+    thesaurus: Option<Thesaurus>,
+    /// Words that are never learned nor suggested.
+    filter: WordFilter,
+    filter_stats: FilterStats,
+    /// Whether learned words are kept out of the backup.
+    incognito: bool,
+    /// Payload reported for words that only exist in a read-only dictionary.
+    blank: V,
 }
 
 impl Autocomplete {
@@ -17,118 +96,1881 @@ impl Autocomplete {
         max_suggestion: Option<u8>,
         has_backup: bool,
         backup_path: Option<&str>,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, ArborError> {
+        Self::build_with_payload(
+            language,
+            thread_count,
+            max_suggestion,
+            has_backup,
+            backup_path,
+        )
+        .await
+    }
+}
+
+impl Autocomplete<Entry> {
+    /// Loads tab-separated `word, description, category, id` records, where
+    /// every column but the word is optional. Returns the number of entries.
+    pub async fn load_entries(&mut self, path: &Path) -> Result<usize, ArborError> {
+        let contents =
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|source| StorageError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+
+        let column = |value: Option<&str>| {
+            value
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let mut count = 0;
+
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut columns = line.split('\t');
+            let word = columns.next().unwrap_or_default().trim().to_string();
+
+            if self.filter.is_filtered(&word) {
+                continue;
+            }
+            let entry = Entry {
+                description: column(columns.next()),
+                category: column(columns.next()),
+                id: column(columns.next()),
+            };
+
+            self.trie
+                .insert_with_value(&word, entry)
+                .map_err(|source| StorageError::Corrupt {
+                    path: path.to_path_buf(),
+                    record: index + 1,
+                    source,
+                })?;
+
+            self.index_word(&word);
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+impl<V: Default> Autocomplete<V> {
+    pub async fn build_with_payload(
+        language: Option<String>,
+        thread_count: Option<u8>,
+        max_suggestion: Option<u8>,
+        has_backup: bool,
+        backup_path: Option<&str>,
+    ) -> Result<Self, ArborError> {
         let app_data = AppData::build(language, thread_count, max_suggestion)?;
         let backup = if has_backup {
             Some(Backup::build(backup_path).await?)
         } else {
             None
         };
-        let trie = Trie::new();
+        let tokenizer = Tokenizer::for_language(app_data.get_language());
+        let mut trie = Trie::with_top_k(app_data.get_max_suggestion() as usize);
+        let phonetic = app_data.get_phonetic().map(PhoneticIndex::new);
+
+        trie.set_half_life(Some(app_data.get_half_life()));
 
         Ok(Self {
             app_data,
             backup,
+            tokenizer,
             trie,
+            dictionaries: Vec::new(),
+            layer_weights: HashMap::new(),
+            hidden: HashSet::new(),
+            hidden_path: None,
+            ranker: Box::new(FrequencyRecency),
+            pattern_limits: PatternLimits::default(),
+            infix: None,
+            phonetic,
+            stems: None,
+            thesaurus: None,
+            filter: WordFilter::new(),
+            filter_stats: FilterStats::default(),
+            incognito: false,
+            blank: V::default(),
         })
     }
 
-    pub async fn load_backup(&mut self) -> Result<(), Box<dyn Error>> {
-        if self.backup.is_some() {
-            let backup = self.backup.as_ref().unwrap();
+    pub fn get_app_data(&self) -> &AppData {
+        &self.app_data
+    }
+
+    /// Replaces how suggestions are ordered; frequency blended with recency by
+    /// default. The new ranker only learns from words committed from now on.
+    pub fn set_ranker(&mut self, ranker: Box<dyn Ranker>) {
+        self.ranker = ranker;
+        self.order_cache();
+    }
+
+    /// Replaces how text is split into words before it's learned.
+    pub fn set_tokenizer(&mut self, tokenizer: Tokenizer) {
+        self.tokenizer = tokenizer;
+    }
+
+    pub fn get_tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    pub fn set_pattern_limits(&mut self, limits: PatternLimits) {
+        self.pattern_limits = limits;
+    }
+
+    /// Builds a substring index over every word known so far, keeping it up
+    /// to date from then on. Words beyond `budget` bytes are left out of it.
+    pub fn enable_infix_index(&mut self, budget: usize) {
+        let mut infix = InfixIndex::new(budget);
+
+        for (word, _, _) in self.trie.iter_prefix("") {
+            infix.insert(&word);
+        }
+
+        for (_, dictionary) in &self.dictionaries {
+            dictionary.for_each(|word, _| {
+                infix.insert(word);
+            });
+        }
+
+        self.infix = Some(infix);
+    }
+
+    pub fn get_infix_index(&self) -> Option<&InfixIndex> {
+        self.infix.as_ref()
+    }
+
+    /// Switches the algorithm used to find sound-alike words, re-encoding
+    /// every word known so far, or turns phonetic matching off with `None`.
+    pub fn set_phonetic(&mut self, algorithm: Option<PhoneticAlgorithm>) {
+        self.app_data.set_phonetic(algorithm);
+        self.phonetic = algorithm.map(|algorithm| {
+            let mut phonetic = PhoneticIndex::new(algorithm);
+
+            for (word, _, _) in self.trie.iter_prefix("") {
+                phonetic.insert(&word);
+            }
+
+            for (_, dictionary) in &self.dictionaries {
+                dictionary.for_each(|word, _| phonetic.insert(word));
+            }
+
+            phonetic
+        });
+    }
+
+    pub fn get_phonetic_index(&self) -> Option<&PhoneticIndex> {
+        self.phonetic.as_ref()
+    }
+
+    /// Groups every learned word by its stem from now on, or stops with
+    /// `None`. Dictionary words have no usage to share, so they aren't grouped.
+    pub fn set_stemmer(&mut self, stemmer: Option<Box<dyn Stemmer>>) {
+        self.stems = stemmer.map(|stemmer| {
+            let mut stems = StemIndex::new(stemmer);
+
+            for (word, _, _) in self.trie.iter_prefix("") {
+                stems.insert(&word);
+            }
+
+            stems
+        });
+    }
+
+    pub fn get_stem_index(&self) -> Option<&StemIndex> {
+        self.stems.as_ref()
+    }
+
+    /// The stem of `word` when stemming is on.
+    pub fn stem(&self, word: &str) -> Option<String> {
+        self.stems.as_ref().map(|stems| stems.stem(word))
+    }
+
+    /// How often `word` and every learned word sharing its stem were used
+    /// together, or just `word` when stemming is off.
+    pub fn stem_frequency(&self, word: &str) -> u32 {
+        let frequency = |word: &str| self.trie.get(word).map_or(0, |(frequency, _)| frequency);
+
+        match &self.stems {
+            Some(stems) => stems
+                .family(word)
+                .into_iter()
+                .fold(0, |total: u32, word| total.saturating_add(frequency(word))),
+            None => frequency(word),
+        }
+    }
+
+    /// Every stem with its combined count and the learned words sharing it,
+    /// most used first.
+    pub fn stem_counts(&self) -> Vec<(String, u32, Vec<String>)> {
+        let Some(stems) = &self.stems else {
+            return Vec::new();
+        };
+
+        let mut counts: Vec<(String, u32, Vec<String>)> = stems
+            .iter()
+            .filter_map(|(stem, words)| {
+                let words: Vec<String> = words
+                    .iter()
+                    .filter(|word| self.trie.get(word).is_some())
+                    .cloned()
+                    .collect();
+                let first = words.first()?;
+
+                Some((stem.to_string(), self.stem_frequency(first), words))
+            })
+            .collect();
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        counts
+    }
+
+    /// Moves suggestions sharing a stem right after the best ranked one of
+    /// them, e.g. "running, rust, runs" becomes "running, runs, rust".
+    pub fn group_by_stem<'a>(&self, suggestions: Vec<Suggestion<'a, V>>) -> Vec<Suggestion<'a, V>> {
+        let Some(stems) = &self.stems else {
+            return suggestions;
+        };
+
+        let mut groups: Vec<(String, Vec<Suggestion<'a, V>>)> = Vec::new();
 
-            let backup_data = backup.load_data().await?;
+        for suggestion in suggestions {
+            let stem = stems.stem(&suggestion.word);
 
-            for word in backup_data {
-                Trie::insert(word, &mut self.trie.root, 0)?;
+            match groups.iter_mut().find(|(known, _)| *known == stem) {
+                Some((_, group)) => group.push(suggestion),
+                None => groups.push((stem, Vec::from([suggestion]))),
             }
         }
 
+        groups.into_iter().flat_map(|(_, group)| group).collect()
+    }
+
+    fn index_word(&mut self, word: &str) {
+        if let Some(infix) = &mut self.infix {
+            infix.insert(word);
+        }
+
+        if let Some(phonetic) = &mut self.phonetic {
+            phonetic.insert(word);
+        }
+
+        if let Some(stems) = &mut self.stems {
+            stems.insert(word);
+        }
+    }
+
+    /// Adds a thesaurus so synonyms and other related words can be offered
+    /// alongside completions.
+    pub fn set_thesaurus(&mut self, thesaurus: Thesaurus) {
+        self.thesaurus = Some(thesaurus);
+    }
+
+    pub fn get_thesaurus(&self) -> Option<&Thesaurus> {
+        self.thesaurus.as_ref()
+    }
+
+    pub fn set_half_life(&mut self, half_life: Duration) {
+        self.app_data.set_half_life(half_life);
+        self.order_cache();
+    }
+
+    /// Orders the trie's top-k cache the way the ranker's bound expects.
+    fn order_cache(&mut self) {
+        let half_life = match self.ranker.cache_order() {
+            CacheOrder::Frequency => None,
+            CacheOrder::Decayed => Some(self.app_data.get_half_life()),
+        };
+
+        self.trie.set_half_life(half_life);
+    }
+
+    /// Layers a read-only dictionary underneath the words learned at runtime.
+    pub fn add_dictionary(&mut self, dictionary: Dictionary, layer: Layer) {
+        if let Some(infix) = &mut self.infix {
+            dictionary.for_each(|word, _| {
+                infix.insert(word);
+            });
+        }
+
+        if let Some(phonetic) = &mut self.phonetic {
+            dictionary.for_each(|word, _| phonetic.insert(word));
+        }
+
+        self.dictionaries.push((layer, dictionary));
+
+        // NOTE: higher layers are searched first so they shadow lower ones
+        self.dictionaries.sort_by_key(|(layer, _)| Reverse(*layer));
+    }
+
+    pub fn get_dictionaries(&self) -> impl Iterator<Item = (Layer, &Dictionary)> {
+        self.dictionaries
+            .iter()
+            .map(|(layer, dictionary)| (*layer, dictionary))
+    }
+
+    /// Scales the scores of every word in `layer`; layers default to a weight of 1.
+    pub fn set_layer_weight(&mut self, layer: Layer, weight: f64) {
+        self.layer_weights.insert(layer, weight);
+    }
+
+    pub fn get_layer_weight(&self, layer: Layer) -> f64 {
+        self.layer_weights.get(&layer).copied().unwrap_or(1.0)
+    }
+
+    /// Loads the words hidden in the personal layer, one per line, and
+    /// remembers the file so later calls to `hide_word` are kept.
+    pub async fn load_hidden(&mut self, path: &Path) -> Result<usize, ArborError> {
+        if path.exists() {
+            let contents =
+                tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|source| StorageError::Read {
+                        path: path.to_path_buf(),
+                        source,
+                    })?;
+
+            self.hidden.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_string),
+            );
+        }
+
+        self.hidden_path = Some(path.to_path_buf());
+
+        Ok(self.hidden.len())
+    }
+
+    /// Stops suggesting `word` from any layer and forgets it if it was learned.
+    pub async fn hide_word(&mut self, word: String) -> Result<(), ArborError> {
+        // NOTE: removing it from the trie first rejects invalid words
+        self.trie.remove(&word)?;
+        self.forget(&word);
+
+        if !self.hidden.insert(word.clone()) {
+            return Ok(());
+        }
+
+        if let Some(path) = &self.hidden_path {
+            let write_error = |source| StorageError::Write {
+                path: path.to_path_buf(),
+                source,
+            };
+
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .map_err(write_error)?;
+
+            file.write_all(format!("{}\n", word).as_bytes())
+                .await
+                .map_err(write_error)?;
+            file.flush().await.map_err(write_error)?;
+        }
+
         Ok(())
     }
 
-    pub async fn insert_word(&mut self, word: String) -> Result<(), Box<dyn Error>> {
-        Trie::insert(word.clone(), &mut self.trie.root, 0)?;
+    pub fn is_hidden(&self, word: &str) -> bool {
+        self.hidden.contains(word)
+    }
 
-        if self.backup.is_some() {
-            self.backup
-                .as_mut()
-                .unwrap()
-                .save_data(Vec::from([word]))
-                .await?;
+    /// Loads the learned words of the backup, streaming it record by record.
+    pub async fn load_backup(&mut self) -> Result<(), ArborError> {
+        let Some(backup) = &self.backup else {
+            return Ok(());
+        };
+
+        let mut batch: Vec<String> = Vec::new();
+        let mut batch_timestamp = None;
+        let mut index = 0;
+
+        backup
+            .for_each_record(|word, timestamp| {
+                index += 1;
+
+                // NOTE: words learned before they were hidden or filtered stay in the file
+                if self.hidden.contains(&word) || self.filter.is_filtered(&word) {
+                    return Ok(());
+                }
+
+                // NOTE: words committed together were saved with the same timestamp
+                if batch_timestamp.is_none() || batch_timestamp != timestamp {
+                    if !batch.is_empty() {
+                        self.ranker.learn(&batch);
+                    }
+
+                    batch.clear();
+                }
+
+                batch.push(word.clone());
+                batch_timestamp = timestamp;
+
+                self.trie
+                    .insert(&word)
+                    .map_err(|source| StorageError::Corrupt {
+                        path: PathBuf::from(&backup.file_path),
+                        record: index,
+                        source,
+                    })?;
+
+                if let Some(timestamp) = timestamp {
+                    self.trie.touch(&word, timestamp)?;
+                }
+
+                if let Some(infix) = &mut self.infix {
+                    infix.insert(&word);
+                }
+
+                if let Some(phonetic) = &mut self.phonetic {
+                    phonetic.insert(&word);
+                }
+
+                if let Some(stems) = &mut self.stems {
+                    stems.insert(&word);
+                }
+
+                Ok::<(), ArborError>(())
+            })
+            .await?;
+
+        if !batch.is_empty() {
+            self.ranker.learn(&batch);
         }
 
         Ok(())
     }
 
-    pub async fn suggest_word(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
-        let suggestions = self.trie.suggest(prefix)?;
+    pub async fn insert_word(&mut self, word: String) -> Result<(), ArborError> {
+        self.insert_words(Vec::from([word])).await
+    }
 
-        let limit = self.app_data.get_max_suggestion() as usize;
+    /// Inserts `word` with an attached payload. Payloads are not written to the backup.
+    pub async fn insert_word_with_value(
+        &mut self,
+        word: String,
+        value: V,
+    ) -> Result<(), ArborError> {
+        self.check_word(&word)?;
+        self.trie.insert_with_value(&word, value)?;
+        self.index_word(&word);
 
-        Ok(suggestions.iter().take(limit).cloned().collect())
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::error::Error;
+    /// Inserts every word before touching the backup so the batch is saved in one write.
+    pub async fn insert_words(&mut self, words: Vec<String>) -> Result<(), ArborError> {
+        for word in &words {
+            self.check_word(word)?;
+        }
 
-    use super::*;
+        let now = unix_time();
 
-    #[tokio::test]
-    async fn it_creates_autocomplete_instance() -> Result<(), Box<dyn Error>> {
-        let ac = Autocomplete::build(None, None, None, false, None).await?;
+        for word in &words {
+            self.trie.insert(word)?;
+            self.trie.touch(word, now)?;
+            self.index_word(word);
+        }
 
-        assert_eq!(ac.app_data.get_language(), "en-US");
-        assert!(ac.backup.is_none());
+        self.ranker.learn(&words);
+
+        if let (false, Some(backup)) = (self.incognito, &self.backup) {
+            backup
+                .save_records(words.into_iter().map(|word| (word, now)).collect())
+                .await?;
+        }
 
         Ok(())
     }
 
-    #[tokio::test]
-    async fn it_loads_backup() -> Result<(), Box<dyn Error>> {
-        let backup = Backup::build(None).await?;
+    /// Tokenizes free text with the language's rules and learns the accepted
+    /// words. Words the filter refuses are reported as rejected.
+    pub async fn learn_text(&mut self, text: &str) -> Result<TokenReport, ArborError> {
+        let mut report = self.tokenizer.tokenize(text);
+        let (accepted, filtered): (Vec<_>, Vec<_>) = report
+            .accepted
+            .drain(..)
+            .map(|token| (self.filter.check(&token.text), token))
+            .partition(|(reason, _)| reason.is_none());
 
-        let words = Vec::from(["hello".to_string(), "hi".to_string(), "hey".to_string()]);
+        report.accepted = accepted.into_iter().map(|(_, token)| token).collect();
 
-        backup.save_data(words.clone()).await?;
+        for (reason, token) in filtered {
+            if let Some(reason) = reason {
+                report.rejected.push(Rejected { token, reason });
+            }
+        }
 
-        let mut ac = Autocomplete::build(None, None, None, true, None).await?;
+        report
+            .rejected
+            .sort_by_key(|rejected| rejected.token.offset);
 
-        ac.load_backup().await?;
+        // NOTE: the tokenizer skips secrets itself, count them along
+        for rejected in &report.rejected {
+            self.filter_stats.record(&rejected.reason);
+        }
 
-        let word_1 = ac.suggest_word("hell").await?;
-        let word_2 = ac.suggest_word("hi").await?;
-        let word_3 = ac.suggest_word("hey").await?;
+        self.insert_words(report.words()).await?;
 
-        assert_eq!(
-            Vec::from([
-                word_1.iter().nth(0).unwrap().to_owned(),
-                word_2.iter().nth(0).unwrap().to_owned(),
-                word_3.iter().nth(0).unwrap().to_owned(),
-            ]),
-            words
-        );
+        Ok(report)
+    }
 
-        std::fs::remove_file(backup.file_path).unwrap();
+    /// Checks that the tokenizer accepts `word` as is and the filter lets it
+    /// through, counting it if the filter doesn't.
+    fn check_word(&mut self, word: &str) -> Result<(), TokenizerError> {
+        self.tokenizer.check(word)?;
+
+        match self.filter.check(word) {
+            Some(reason) => {
+                self.filter_stats.record(&reason);
+
+                Err(TokenizerError::Rejected {
+                    token: word.to_string(),
+                    offset: 0,
+                    reason,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the words that are never learned nor suggested, forgetting
+    /// the learned words it refuses. The backup keeps them.
+    pub fn set_filter(&mut self, filter: WordFilter) {
+        let refused: Vec<String> = self
+            .trie
+            .iter_prefix("")
+            .map(|(word, _, _)| word)
+            .filter(|word| filter.is_filtered(word))
+            .collect();
+
+        for word in &refused {
+            self.forget(word);
+        }
+
+        self.filter = filter;
+    }
+
+    /// Removes a learned word from the trie and every index.
+    fn forget(&mut self, word: &str) {
+        // NOTE: only words listed from the trie are forgotten, so they are valid
+        let _ = self.trie.remove(word);
+
+        if let Some(infix) = &mut self.infix {
+            infix.remove(word);
+        }
+
+        if let Some(phonetic) = &mut self.phonetic {
+            phonetic.remove(word);
+        }
+
+        if let Some(stems) = &mut self.stems {
+            stems.remove(word);
+        }
+    }
+
+    /// Forgets every learned word matching a pattern such as `pass*` or
+    /// `hunter?`, both in memory and in the backup, even in incognito mode.
+    /// Returns the words purged, alphabetically.
+    pub async fn purge(&mut self, pattern: &str) -> Result<Vec<String>, ArborError> {
+        let pattern = Pattern::parse(pattern)?;
+        let mut purged: BTreeSet<String> = self
+            .trie
+            .iter_prefix("")
+            .map(|(word, _, _)| word)
+            .filter(|word| pattern.matches(word))
+            .collect();
+
+        for word in &purged {
+            self.forget(word);
+        }
+
+        if let Some(backup) = &self.backup {
+            purged.extend(backup.retain(|word| !pattern.matches(word)).await?);
+        }
+
+        Ok(purged.into_iter().collect())
+    }
+
+    pub fn get_backup(&self) -> Option<&Backup> {
+        self.backup.as_ref()
+    }
+
+    /// Compresses the backup into segments once it outgrows `segment_size`
+    /// bytes. Does nothing without a backup.
+    pub fn set_backup_compression(&mut self, compression: Compression, segment_size: u64) {
+        if let Some(backup) = &mut self.backup {
+            backup.set_compression(compression);
+            backup.set_segment_size(segment_size);
+        }
+    }
+
+    /// Unlocks an encrypted backup with the key from `source`, or encrypts a
+    /// plain one with it. Does nothing without a backup.
+    pub async fn unlock_backup(&mut self, source: &KeySource) -> Result<(), ArborError> {
+        if let Some(backup) = &mut self.backup {
+            backup.unlock(source).await?;
+        }
 
         Ok(())
     }
 
-    #[tokio::test]
-    async fn it_inserts_word_and_suggests() -> Result<(), Box<dyn Error>> {
-        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+    /// Re-encrypts the unlocked backup with a new key from `source`.
+    pub async fn rotate_backup_key(&mut self, source: &KeySource) -> Result<(), ArborError> {
+        if let Some(backup) = &mut self.backup {
+            backup.rotate(source).await?;
+        }
 
-        let word = "test".to_string();
+        Ok(())
+    }
 
-        ac.insert_word(word.clone()).await?;
+    /// Keeps everything learned from now on in memory only, so nothing is
+    /// written to the backup until it is turned off.
+    pub fn set_incognito(&mut self, incognito: bool) {
+        self.incognito = incognito;
+    }
 
-        let suggestion = ac.suggest_word(word.as_str()).await?;
+    pub fn is_incognito(&self) -> bool {
+        self.incognito
+    }
 
-        let suggestion = suggestion.iter().nth(0).unwrap().to_owned();
+    pub fn get_filter(&self) -> &WordFilter {
+        &self.filter
+    }
 
-        assert_eq!(word, suggestion);
+    /// How many inputs the filter kept from being learned so far.
+    pub fn get_filter_stats(&self) -> &FilterStats {
+        &self.filter_stats
+    }
+
+    /// Whether `word` must not be suggested, because it was hidden or the
+    /// filter refuses it.
+    fn is_excluded(&self, word: &str) -> bool {
+        self.hidden.contains(word) || self.filter.is_filtered(word)
+    }
+
+    pub async fn suggest_word(&self, prefix: &str) -> Result<Vec<String>, ArborError> {
+        Ok(self
+            .suggest_word_with_details(prefix)
+            .await?
+            .into_iter()
+            .map(|(word, _, _)| word)
+            .collect())
+    }
+
+    pub async fn suggest_word_with_frequency(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<(String, u32)>, ArborError> {
+        Ok(self
+            .suggest_word_with_details(prefix)
+            .await?
+            .into_iter()
+            .map(|(word, frequency, _)| (word, frequency))
+            .collect())
+    }
+
+    pub async fn suggest_word_with_values(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<(String, &V)>, ArborError> {
+        Ok(self
+            .suggest_word_with_details(prefix)
+            .await?
+            .into_iter()
+            .map(|(word, _, value)| (word, value))
+            .collect())
+    }
+
+    pub async fn suggest_word_with_details(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<(String, u32, &V)>, ArborError> {
+        Ok(self
+            .suggest_word_with_layers(prefix)
+            .await?
+            .into_iter()
+            .map(|suggestion| (suggestion.word, suggestion.score, suggestion.value))
+            .collect())
+    }
+
+    pub async fn suggest_word_with_layers(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Suggestion<'_, V>>, ArborError> {
+        self.suggest_word_after(None, prefix).await
+    }
+
+    /// Merges the learned words with every dictionary layer. A word is scored
+    /// by the ranker in the highest layer that has it, scaled by that layer's
+    /// weight. `previous` is the word typed before `prefix`, for context rankers.
+    pub async fn suggest_word_after(
+        &self,
+        previous: Option<&str>,
+        prefix: &str,
+    ) -> Result<Vec<Suggestion<'_, V>>, ArborError> {
+        let limit = self.app_data.get_max_suggestion() as usize;
+        let context = Context {
+            now: unix_time(),
+            half_life: self.app_data.get_half_life(),
+            previous,
+        };
+        let weighted = |layer: Layer, score: u64| self.weighted(layer, score);
+        let rank = |word: &str, frequency: u32, last_used: Option<u64>, layer: Layer| {
+            self.rank(word, frequency, last_used, layer, &context)
+        };
+
+        let mut ranked: Vec<(f64, Suggestion<'_, V>)> = Vec::new();
+
+        for (word, frequency, value) in self.learned_candidates(prefix, limit, &context) {
+            if self.is_excluded(&word) {
+                continue;
+            }
+
+            let score = rank(
+                &word,
+                frequency,
+                self.trie.last_used(&word),
+                Layer::Personal,
+            );
+
+            ranked.push((
+                score,
+                Suggestion {
+                    word,
+                    score: weighted(Layer::Personal, frequency as u64),
+                    value,
+                    layer: Layer::Personal,
+                },
+            ));
+        }
+
+        // NOTE: over-fetch so hidden words don't leave the list short; filtered
+        // dictionary words may still do
+        let fetch = limit + self.hidden.len();
+
+        for (layer, dictionary) in &self.dictionaries {
+            for (word, weight) in dictionary.suggest(prefix, fetch) {
+                if self.is_excluded(&word) || ranked.iter().any(|(_, known)| known.word == word) {
+                    continue;
+                }
+
+                let frequency = u32::try_from(weight).unwrap_or(u32::MAX);
+
+                ranked.push((
+                    rank(&word, frequency, None, *layer),
+                    Suggestion {
+                        word,
+                        score: weighted(*layer, weight),
+                        value: &self.blank,
+                        layer: *layer,
+                    },
+                ));
+            }
+        }
+
+        ranked.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.total_cmp(a_score).then_with(|| a.word.cmp(&b.word))
+        });
+
+        Ok(ranked
+            .into_iter()
+            .take(limit)
+            .map(|(_, suggestion)| suggestion)
+            .collect())
+    }
+
+    /// Words containing `fragment` anywhere, e.g. "import" for "port", with
+    /// the words starting with it first. Without an infix index only those
+    /// prefix matches are returned.
+    pub async fn suggest_containing(
+        &self,
+        previous: Option<&str>,
+        fragment: &str,
+    ) -> Result<Vec<Suggestion<'_, V>>, ArborError> {
+        let limit = self.app_data.get_max_suggestion() as usize;
+        let mut suggestions = self.suggest_word_after(previous, fragment).await?;
+
+        let Some(infix) = &self.infix else {
+            return Ok(suggestions);
+        };
+
+        let found = infix
+            .find(fragment)
+            .into_iter()
+            .filter(|word| !word.starts_with(fragment));
+        let room = limit.saturating_sub(suggestions.len());
+
+        suggestions.extend(self.rank_found(previous, found).into_iter().take(room));
+
+        Ok(suggestions)
+    }
+
+    /// The words typed so far followed by words that sound like `prefix`,
+    /// e.g. "Philip" for "Fillip". Sound-alikes always rank below the words
+    /// that really start with `prefix` and only fill the room they leave.
+    pub async fn suggest_phonetic(
+        &self,
+        previous: Option<&str>,
+        prefix: &str,
+    ) -> Result<Vec<Suggestion<'_, V>>, ArborError> {
+        let limit = self.app_data.get_max_suggestion() as usize;
+        let mut suggestions = self.suggest_word_after(previous, prefix).await?;
+
+        let Some(phonetic) = &self.phonetic else {
+            return Ok(suggestions);
+        };
+
+        if prefix.chars().count() < MIN_PHONETIC_LENGTH || suggestions.len() >= limit {
+            return Ok(suggestions);
+        }
+
+        let found = phonetic
+            .find(prefix)
+            .into_iter()
+            .filter(|word| !suggestions.iter().any(|known| known.word == *word));
+        let room = limit - suggestions.len();
+        let found = self.rank_found(previous, found);
+
+        suggestions.extend(found.into_iter().take(room));
+
+        Ok(suggestions)
+    }
+
+    /// Words related to `word` in the thesaurus, grouped by relation with the
+    /// most used first. Hidden words are left out.
+    pub fn related_words(&self, word: &str) -> Vec<RelatedWord> {
+        let Some(thesaurus) = &self.thesaurus else {
+            return Vec::new();
+        };
+
+        let entries = match thesaurus.lookup(word) {
+            [] => thesaurus.lookup(&word.to_lowercase()),
+            entries => entries,
+        };
+
+        let frequency = |word: &str| match self.trie.get(word) {
+            Some((frequency, _)) => self.weighted(Layer::Personal, frequency as u64),
+            None => self
+                .dictionaries
+                .iter()
+                .find_map(|(layer, dictionary)| {
+                    Some(self.weighted(*layer, dictionary.get_weight(word)?))
+                })
+                .unwrap_or(0),
+        };
+
+        let mut related: Vec<(u32, RelatedWord)> = entries
+            .iter()
+            .filter(|(_, related)| !self.is_excluded(related))
+            .map(|(relation, related)| {
+                (
+                    frequency(related),
+                    RelatedWord {
+                        word: related.clone(),
+                        relation: *relation,
+                    },
+                )
+            })
+            .collect();
+
+        related.sort_by(|(a_frequency, a), (b_frequency, b)| {
+            a.relation
+                .cmp(&b.relation)
+                .then_with(|| b_frequency.cmp(a_frequency))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+
+        related
+            .into_iter()
+            .take(self.app_data.get_max_suggestion() as usize)
+            .map(|(_, related)| related)
+            .collect()
+    }
+
+    /// Completions for `prefix` and the words related to it as typed.
+    pub async fn suggest_with_related(
+        &self,
+        previous: Option<&str>,
+        prefix: &str,
+    ) -> Result<Completions<'_, V>, ArborError> {
+        Ok(Completions {
+            suggestions: self.suggest_word_after(previous, prefix).await?,
+            related: self.related_words(prefix),
+        })
+    }
+
+    /// Whether `word` is learned or in any dictionary layer. A capitalized word
+    /// also counts as known when its lowercase form is, e.g. at the start of
+    /// a sentence.
+    pub fn is_known(&self, word: &str) -> bool {
+        let known = |word: &str| {
+            self.trie.search(word).unwrap_or(false)
+                || self
+                    .dictionaries
+                    .iter()
+                    .any(|(_, dictionary)| dictionary.contains(word))
+        };
+
+        known(word) || known(&word.to_lowercase())
+    }
+
+    /// Known words within `max_distance` edits of `word`, closest first and
+    /// then most used. A word in several layers is scored by the highest one.
+    pub fn corrections(&self, word: &str, max_distance: usize) -> Vec<Correction> {
+        let levenshtein = Levenshtein::new(word, max_distance);
+        let mut corrections: Vec<Correction> = self
+            .trie
+            .find_similar(&levenshtein)
+            .into_iter()
+            .map(|(word, distance, frequency)| Correction {
+                frequency: self.weighted(Layer::Personal, frequency as u64),
+                word,
+                distance,
+            })
+            .collect();
+
+        for (layer, dictionary) in &self.dictionaries {
+            for (word, distance, weight) in dictionary.find_similar(&levenshtein) {
+                if corrections.iter().any(|known| known.word == word) {
+                    continue;
+                }
+
+                corrections.push(Correction {
+                    word,
+                    distance,
+                    frequency: self.weighted(*layer, weight),
+                });
+            }
+        }
+
+        corrections.retain(|correction| !self.is_excluded(&correction.word));
+        corrections.sort_by(|a, b| {
+            (a.distance, Reverse(a.frequency), &a.word).cmp(&(
+                b.distance,
+                Reverse(b.frequency),
+                &b.word,
+            ))
+        });
+        corrections.truncate(self.app_data.get_max_suggestion() as usize);
+
+        corrections
+    }
+
+    /// Tokenizes `text` with the language's rules and flags every word that
+    /// isn't known, with corrections up to `max_distance` edits away.
+    pub async fn check_text(&self, text: &str, max_distance: usize) -> Vec<Misspelling> {
+        self.tokenizer
+            .tokenize(text)
+            .accepted
+            .into_iter()
+            .filter(|token| !self.is_known(&token.text))
+            .map(|token| Misspelling {
+                corrections: self.corrections(&token.text, max_distance),
+                word: token.text,
+                offset: token.offset,
+            })
+            .collect()
+    }
+
+    /// Ranks words found by one of the indexes, looking each one up in the
+    /// highest layer that has it. Hidden and forgotten words are dropped.
+    fn rank_found<'a, 'b>(
+        &'a self,
+        previous: Option<&str>,
+        words: impl Iterator<Item = &'b str>,
+    ) -> Vec<Suggestion<'a, V>> {
+        let context = Context {
+            now: unix_time(),
+            half_life: self.app_data.get_half_life(),
+            previous,
+        };
+        let mut ranked: Vec<(f64, Suggestion<'_, V>)> = Vec::new();
+
+        for word in words {
+            if self.is_excluded(word) {
+                continue;
+            }
+
+            let (frequency, value, layer, last_used) =
+                match self.trie.get(word) {
+                    Some((frequency, value)) => (
+                        frequency as u64,
+                        value,
+                        Layer::Personal,
+                        self.trie.last_used(word),
+                    ),
+                    None => match self.dictionaries.iter().find_map(|(layer, dictionary)| {
+                        Some((*layer, dictionary.get_weight(word)?))
+                    }) {
+                        Some((layer, weight)) => (weight, &self.blank, layer, None),
+                        // NOTE: the word was forgotten after it was indexed
+                        None => continue,
+                    },
+                };
+
+            let score = u32::try_from(frequency).unwrap_or(u32::MAX);
+
+            ranked.push((
+                self.rank(word, score, last_used, layer, &context),
+                Suggestion {
+                    word: word.to_string(),
+                    score: self.weighted(layer, frequency),
+                    value,
+                    layer,
+                },
+            ));
+        }
+
+        ranked.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.total_cmp(a_score).then_with(|| a.word.cmp(&b.word))
+        });
+
+        ranked
+            .into_iter()
+            .map(|(_, suggestion)| suggestion)
+            .collect()
+    }
+
+    /// Scores a word with the ranker, scaled by its layer's weight.
+    fn rank(
+        &self,
+        word: &str,
+        frequency: u32,
+        last_used: Option<u64>,
+        layer: Layer,
+        context: &Context,
+    ) -> f64 {
+        let candidate = Candidate {
+            word,
+            frequency,
+            last_used,
+            layer,
+            stem_frequency: self.stem_frequency(word).max(frequency),
+        };
+
+        self.ranker.score(&candidate, context) * self.get_layer_weight(layer)
+    }
+
+    /// A word's count scaled by its layer's weight, as shown to the user.
+    fn weighted(&self, layer: Layer, score: u64) -> u32 {
+        let score = (score as f64 * self.get_layer_weight(layer)).round();
+
+        score.clamp(0.0, u32::MAX as f64) as u32
+    }
+
+    /// Finds words matching a pattern such as `h?ll*` or `c[ao]t` in every
+    /// layer, within the configured result and visited-node limits.
+    pub async fn match_pattern(&self, pattern: &str) -> Result<PatternMatches<'_, V>, ArborError> {
+        let pattern = Pattern::parse(pattern)?;
+        let limits = self.pattern_limits;
+        let matches = self.trie.find_matches(&pattern, limits);
+
+        let mut truncated = matches.truncated;
+        let mut suggestions: Vec<Suggestion<'_, V>> = matches
+            .words
+            .into_iter()
+            .filter(|(word, _, _)| !self.is_excluded(word))
+            .map(|(word, frequency, value)| Suggestion {
+                word,
+                score: frequency,
+                value,
+                layer: Layer::Personal,
+            })
+            .collect();
+
+        for (layer, dictionary) in &self.dictionaries {
+            let (words, more) = dictionary.find_matches(&pattern, limits.max_results);
+            truncated |= more;
+
+            for (word, weight) in words {
+                if self.is_excluded(&word) || suggestions.iter().any(|known| known.word == word) {
+                    continue;
+                }
+
+                suggestions.push(Suggestion {
+                    word,
+                    score: u32::try_from(weight).unwrap_or(u32::MAX),
+                    value: &self.blank,
+                    layer: *layer,
+                });
+            }
+        }
+
+        suggestions.sort_by(|a, b| a.word.cmp(&b.word));
+
+        if suggestions.len() > limits.max_results {
+            suggestions.truncate(limits.max_results);
+            truncated = true;
+        }
+
+        Ok(PatternMatches {
+            suggestions,
+            truncated,
+        })
+    }
+
+    /// Learned words that could make the top `limit`. The trie's cached words
+    /// are enough when none of them is excluded and nothing outside them can
+    /// outscore them, otherwise the whole subtree is scored.
+    fn learned_candidates(
+        &self,
+        prefix: &str,
+        limit: usize,
+        context: &Context,
+    ) -> Vec<(String, u32, &V)> {
+        let top = self.trie.top(prefix, limit);
+
+        if top.len() < limit {
+            return top;
+        }
+
+        let candidates: Vec<Candidate> = top
+            .iter()
+            .map(|(word, frequency, _)| Candidate {
+                word,
+                frequency: *frequency,
+                last_used: self.trie.last_used(word),
+                layer: Layer::Personal,
+                stem_frequency: self.stem_frequency(word),
+            })
+            .collect();
+        let kth_score = candidates
+            .iter()
+            .map(|candidate| self.ranker.score(candidate, context))
+            .fold(f64::INFINITY, f64::min);
+        let Some(lowest) = candidates.last() else {
+            return top;
+        };
+
+        let order = self.ranker.cache_order();
+        let excluded = top.iter().any(|(word, _, _)| self.is_excluded(word));
+
+        match self.ranker.upper_bound(lowest, order, context) {
+            Some(bound) if kth_score >= bound && !excluded => top,
+            _ => self.trie.iter_prefix(prefix).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::iter_nth, clippy::iter_nth_zero)]
+mod tests {
+    use std::error::Error;
+
+    use crate::common::{
+        filter::WordFilter, infix::DEFAULT_BUDGET, ranking, stemmer, thesaurus::Relation,
+        tokenizer::Rejection,
+    };
+    use crate::util::scratch::Scratch;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_creates_autocomplete_instance() -> Result<(), ArborError> {
+        let ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        assert_eq!(ac.app_data.get_language(), "en-US");
+        assert!(ac.backup.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_loads_backup() -> Result<(), ArborError> {
+        let backup = Backup::build(None).await?;
+
+        let words = Vec::from(["hello".to_string(), "hi".to_string(), "hey".to_string()]);
+
+        backup.save_data(words.clone()).await?;
+
+        let mut ac = Autocomplete::build(None, None, None, true, None).await?;
+
+        ac.load_backup().await?;
+
+        let word_1 = ac.suggest_word("hell").await?;
+        let word_2 = ac.suggest_word("hi").await?;
+        let word_3 = ac.suggest_word("hey").await?;
+
+        assert_eq!(
+            Vec::from([
+                word_1.iter().nth(0).unwrap().to_owned(),
+                word_2.iter().nth(0).unwrap().to_owned(),
+                word_3.iter().nth(0).unwrap().to_owned(),
+            ]),
+            words
+        );
+
+        std::fs::remove_file(backup.file_path).unwrap();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_inserts_word_and_suggests() -> Result<(), ArborError> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        let word = "test".to_string();
+
+        ac.insert_word(word.clone()).await?;
+
+        let suggestion = ac.suggest_word(word.as_str()).await?;
+
+        let suggestion = suggestion.iter().nth(0).unwrap().to_owned();
+
+        assert_eq!(word, suggestion);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_rejects_invalid_words() -> Result<(), Box<dyn Error>> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        let error = ac.insert_word("hello,".to_string()).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            ArborError::Tokenizer(TokenizerError::Rejected {
+                reason: Rejection::InvalidCharacter(','),
+                ..
+            })
+        ));
+        assert!(ac.suggest_word("hel").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_loads_entries() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let path = scratch.path("entries");
+        std::fs::write(&path, "widget\tA small widget\ttools\tSKU1\nwidth\n")?;
+
+        let mut ac =
+            Autocomplete::<Entry>::build_with_payload(None, None, None, false, None).await?;
+
+        assert_eq!(ac.load_entries(&path).await?, 2);
+
+        let mut suggestions = ac.suggest_word_with_values("wid").await?;
+        suggestions.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            suggestions[0],
+            (
+                "widget".to_string(),
+                &Entry {
+                    description: Some("A small widget".to_string()),
+                    category: Some("tools".to_string()),
+                    id: Some("SKU1".to_string()),
+                }
+            )
+        );
+        assert_eq!(suggestions[1], ("width".to_string(), &Entry::default()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_layers_dictionaries_under_learned_words() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let mut ac = Autocomplete::build(None, None, Some(3), false, None).await?;
+
+        ac.add_dictionary(
+            scratch.dictionary("words", "hello\t5\nhelp\t3\nhelium\t1\n")?,
+            Layer::System,
+        );
+        ac.insert_word("helium".to_string()).await?;
+        ac.insert_word("helmet".to_string()).await?;
+
+        assert_eq!(
+            ac.suggest_word_with_frequency("hel").await?,
+            Vec::from([
+                ("hello".to_string(), 5),
+                ("help".to_string(), 3),
+                ("helium".to_string(), 1),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_weighs_and_hides_layers() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let hidden = scratch.path("hidden");
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.add_dictionary(
+            scratch.dictionary("system", "deploy\t4\ndebug\t6\ndelta\t5\n")?,
+            Layer::System,
+        );
+        ac.add_dictionary(
+            scratch.dictionary("team", "deploy\t1\ndevbox\t2\n")?,
+            Layer::Team,
+        );
+        ac.set_layer_weight(Layer::Team, 10.0);
+        ac.load_hidden(&hidden).await?;
+        ac.hide_word("debug".to_string()).await?;
+
+        let layers = ac
+            .suggest_word_with_layers("de")
+            .await?
+            .into_iter()
+            .map(|suggestion| (suggestion.word, suggestion.score, suggestion.layer))
+            .collect::<Vec<(String, u32, Layer)>>();
+
+        assert_eq!(
+            layers,
+            Vec::from([
+                ("devbox".to_string(), 20, Layer::Team),
+                ("deploy".to_string(), 10, Layer::Team),
+                ("delta".to_string(), 5, Layer::System),
+            ])
+        );
+        assert_eq!(std::fs::read_to_string(&hidden)?, "debug\n");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_keeps_hidden_words_hidden_after_a_restart() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let backup = scratch.path("backup");
+        let hidden = scratch.path("hidden");
+
+        let mut ac = Autocomplete::build(None, None, None, true, backup.to_str()).await?;
+
+        ac.load_hidden(&hidden).await?;
+        ac.insert_words(Vec::from(["secretword".to_string(), "second".to_string()]))
+            .await?;
+        ac.hide_word("secretword".to_string()).await?;
+
+        let mut restarted = Autocomplete::build(None, None, None, true, backup.to_str()).await?;
+
+        restarted.load_hidden(&hidden).await?;
+        restarted.load_backup().await?;
+
+        assert_eq!(restarted.suggest_word("sec").await?, ["second"]);
+
+        // NOTE: hidden words loaded after the backup are excluded when suggesting
+        let mut restarted = Autocomplete::build(None, None, None, true, backup.to_str()).await?;
+
+        restarted.load_backup().await?;
+        restarted.load_hidden(&hidden).await?;
+
+        assert!(restarted.is_hidden("secretword"));
+        assert_eq!(restarted.suggest_word("sec").await?, ["second"]);
+        assert_eq!(
+            restarted
+                .match_pattern("sec*")
+                .await?
+                .suggestions
+                .into_iter()
+                .map(|suggestion| suggestion.word)
+                .collect::<Vec<String>>(),
+            ["second"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_ranks_recent_words_first() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+        let day = 24 * 60 * 60;
+        let month_ago = unix_time() - 30 * day;
+
+        let backup = Backup::build(path.to_str()).await?;
+        let mut records = vec![("stale".to_string(), month_ago); 8];
+        records.push(("steady".to_string(), unix_time()));
+        records.push(("steady".to_string(), unix_time()));
+        backup.save_records(records).await?;
+
+        let mut ac = Autocomplete::build(None, None, Some(1), true, path.to_str()).await?;
+
+        ac.load_backup().await?;
+
+        assert_eq!(ac.suggest_word("st").await?, Vec::from(["steady"]));
+
+        ac.set_half_life(Duration::from_secs(365 * day));
+
+        assert_eq!(ac.suggest_word("st").await?, Vec::from(["stale"]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_serves_recent_words_from_the_cache() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+        let day = 24 * 60 * 60;
+        let month_ago = unix_time() - 30 * day;
+
+        let backup = Backup::build(path.to_str()).await?;
+        let mut records = vec![("stale".to_string(), month_ago); 8];
+        records.push(("stone".to_string(), month_ago));
+        records.push(("steady".to_string(), unix_time()));
+        records.push(("steady".to_string(), unix_time()));
+        records.push(("still".to_string(), unix_time()));
+        backup.save_records(records).await?;
+
+        let mut ac = Autocomplete::build(None, None, Some(2), true, path.to_str()).await?;
+
+        ac.load_backup().await?;
+
+        let context = Context {
+            now: unix_time(),
+            half_life: ac.get_app_data().get_half_life(),
+            previous: None,
+        };
+        let words = |ac: &Autocomplete| -> Vec<String> {
+            ac.learned_candidates("st", 2, &context)
+                .into_iter()
+                .map(|(word, _, _)| word)
+                .collect()
+        };
+
+        // NOTE: only the cached words come back when the bound holds
+        assert_eq!(words(&ac), ["steady", "still"]);
+        assert_eq!(ac.suggest_word("st").await?, ["steady", "still"]);
+
+        ac.hide_word("still".to_string()).await?;
+
+        assert_eq!(words(&ac), ["steady", "stale"]);
+        assert_eq!(ac.suggest_word("st").await?, ["steady", "stale"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_ranks_by_previous_word() -> Result<(), ArborError> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.set_ranker(ranking::parse("frequency=1,context=10")?);
+        ac.learn_text("morning morning morning").await?;
+        ac.learn_text("good movie").await?;
+
+        let after = |suggestions: Vec<Suggestion<'_, ()>>| {
+            suggestions
+                .into_iter()
+                .map(|suggestion| suggestion.word)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            after(ac.suggest_word_after(Some("good"), "mo").await?),
+            Vec::from(["movie", "morning"])
+        );
+        assert_eq!(
+            after(ac.suggest_word_after(None, "mo").await?),
+            Vec::from(["morning", "movie"])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_matches_patterns_across_layers() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.add_dictionary(
+            scratch.dictionary("words", "cat\ncot\ncoat\n")?,
+            Layer::System,
+        );
+        ac.insert_words(Vec::from(["cut".to_string(), "cot".to_string()]))
+            .await?;
+
+        let matches = ac.match_pattern("c?t").await?;
+
+        assert!(!matches.truncated);
+        assert_eq!(
+            matches
+                .suggestions
+                .iter()
+                .map(|suggestion| (suggestion.word.as_str(), suggestion.layer))
+                .collect::<Vec<(&str, Layer)>>(),
+            Vec::from([
+                ("cat", Layer::System),
+                ("cot", Layer::Personal),
+                ("cut", Layer::Personal),
+            ])
+        );
+
+        ac.set_pattern_limits(PatternLimits {
+            max_results: 2,
+            max_visited: 100,
+        });
+
+        assert!(ac.match_pattern("c*").await?.truncated);
+        assert!(ac.match_pattern("c[at").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_suggests_words_containing_fragment() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.insert_words(Vec::from(["import".to_string(), "portal".to_string()]))
+            .await?;
+
+        assert_eq!(
+            ac.suggest_containing(None, "port").await?.len(),
+            1,
+            "only prefix matches without an index"
+        );
+
+        ac.enable_infix_index(DEFAULT_BUDGET);
+        ac.add_dictionary(
+            scratch.dictionary("words", "export\t4\nportable\t1\n")?,
+            Layer::System,
+        );
+        ac.insert_word("report".to_string()).await?;
+        ac.insert_word("report".to_string()).await?;
+
+        let words = ac
+            .suggest_containing(None, "port")
+            .await?
+            .into_iter()
+            .map(|suggestion| suggestion.word)
+            .collect::<Vec<String>>();
+
+        assert_eq!(words, ["portable", "portal", "export", "report", "import"]);
+
+        ac.hide_word("report".to_string()).await?;
+
+        assert!(!ac
+            .get_infix_index()
+            .is_some_and(|infix| infix.find("port").contains(&"report")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_suggests_words_that_sound_alike() -> Result<(), Box<dyn Error>> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.insert_words(Vec::from([
+            "Philip".to_string(),
+            "filling".to_string(),
+            "Fiona".to_string(),
+        ]))
+        .await?;
+
+        let words = |suggestions: Vec<Suggestion<'_, ()>>| {
+            suggestions
+                .into_iter()
+                .map(|suggestion| suggestion.word)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            words(ac.suggest_phonetic(None, "Fillip").await?),
+            ["Philip"]
+        );
+        assert_eq!(
+            words(ac.suggest_phonetic(None, "fil").await?),
+            ["filling", "Philip"],
+            "sound-alikes come after prefix matches"
+        );
+        assert!(ac.suggest_phonetic(None, "Fi").await?.len() == 1);
+
+        ac.set_phonetic(Some(PhoneticAlgorithm::Soundex));
+
+        assert_eq!(
+            ac.get_phonetic_index().map(|index| index.get_algorithm()),
+            Some(PhoneticAlgorithm::Soundex)
+        );
+        assert_eq!(
+            words(ac.suggest_phonetic(None, "Phelyp").await?),
+            ["Philip"]
+        );
+
+        ac.set_phonetic(None);
+
+        assert!(ac.suggest_phonetic(None, "Fillip").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_checks_spelling() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.add_dictionary(
+            scratch.dictionary("words", "the\t50\nten\t5\nquick\t3\nbrown\t3\n")?,
+            Layer::System,
+        );
+        ac.insert_word("fox".to_string()).await?;
+
+        let text = "The quikc brown fxo";
+        let misspellings = ac.check_text(text, 2).await;
+        let flagged = misspellings
+            .iter()
+            .map(|misspelling| (misspelling.word.as_str(), misspelling.offset))
+            .collect::<Vec<(&str, usize)>>();
+
+        assert_eq!(flagged, [("quikc", 4), ("fxo", 16)]);
+        assert_eq!(misspellings[0].corrections[0].word, "quick");
+        assert_eq!(misspellings[1].corrections[0].word, "fox");
+
+        let corrections = ac
+            .corrections("teh", 2)
+            .into_iter()
+            .map(|correction| (correction.word, correction.distance))
+            .collect::<Vec<(String, usize)>>();
+
+        assert_eq!(
+            corrections,
+            [("ten".to_string(), 1), ("the".to_string(), 2)],
+            "closer words rank above more frequent ones"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_suggests_related_words() -> Result<(), Box<dyn Error>> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        assert!(ac.related_words("happy").is_empty());
+
+        let mut thesaurus = Thesaurus::new();
+
+        thesaurus.insert("happy", Relation::Synonym, "glad");
+        thesaurus.insert("happy", Relation::Synonym, "cheerful");
+        thesaurus.insert("happy", Relation::Antonym, "sad");
+        thesaurus.insert("happy", Relation::Synonym, "jolly");
+
+        ac.set_thesaurus(thesaurus);
+        ac.insert_words(Vec::from(["happy".to_string(), "glad".to_string()]))
+            .await?;
+        ac.insert_word("glad".to_string()).await?;
+        ac.insert_word("cheerful".to_string()).await?;
+        ac.hide_word("jolly".to_string()).await?;
+
+        let completions = ac.suggest_with_related(None, "Happy").await?;
+        let related = completions
+            .related
+            .iter()
+            .map(|related| (related.relation, related.word.as_str()))
+            .collect::<Vec<(Relation, &str)>>();
+
+        assert!(completions.suggestions.is_empty());
+        assert_eq!(
+            related,
+            [
+                (Relation::Synonym, "glad"),
+                (Relation::Synonym, "cheerful"),
+                (Relation::Antonym, "sad")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_aggregates_words_by_stem() -> Result<(), Box<dyn Error>> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.insert_words(Vec::from([
+            "running".to_string(),
+            "running".to_string(),
+            "rust".to_string(),
+            "rust".to_string(),
+        ]))
+        .await?;
+        ac.set_stemmer(stemmer::for_language("en-US"));
+        ac.insert_word("runs".to_string()).await?;
+        ac.set_ranker(ranking::parse("frequency")?);
+
+        assert_eq!(ac.stem_frequency("runs"), 3);
+        assert_eq!(
+            ac.stem_counts(),
+            [
+                (
+                    "run".to_string(),
+                    3,
+                    Vec::from(["running".to_string(), "runs".to_string()])
+                ),
+                ("rust".to_string(), 2, Vec::from(["rust".to_string()]))
+            ]
+        );
+
+        let suggestions = ac.suggest_word_after(None, "ru").await?;
+        let words = |suggestions: &[Suggestion<'_, ()>]| {
+            suggestions
+                .iter()
+                .map(|suggestion| suggestion.word.clone())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(words(&suggestions), ["running", "rust", "runs"]);
+        assert_eq!(
+            words(&ac.group_by_stem(suggestions)),
+            ["running", "runs", "rust"]
+        );
+
+        ac.set_ranker(ranking::parse("stem")?);
+
+        assert_eq!(
+            words(&ac.suggest_word_after(None, "ru").await?),
+            ["running", "runs", "rust"],
+            "runs shares the uses of running"
+        );
+
+        ac.hide_word("running".to_string()).await?;
+
+        assert_eq!(ac.stem_frequency("runs"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_keeps_filtered_backup_words_out_of_the_ranker() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+
+        std::fs::write(
+            &path,
+            "good\t1700000000\nbogus\t1700000000\nmovie\t1700000000\n\
+             morning\t1700000100\nmorning\t1700000100\nmorning\t1700000100\n",
+        )?;
+
+        let mut ac = Autocomplete::build(None, None, None, true, path.to_str()).await?;
+        let mut filter = WordFilter::new();
+
+        filter.block("bogus");
+        ac.set_filter(filter);
+        ac.set_ranker(ranking::parse("frequency=1,context=10")?);
+        ac.load_backup().await?;
+
+        let words = ac
+            .suggest_word_after(Some("good"), "mo")
+            .await?
+            .into_iter()
+            .map(|suggestion| suggestion.word)
+            .collect::<Vec<String>>();
+
+        assert_eq!(words, ["movie", "morning"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_filters_stop_words_and_blocked_words() -> Result<(), Box<dyn Error>> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.insert_words(Vec::from(["then".to_string(), "thermal".to_string()]))
+            .await?;
+
+        let mut filter = WordFilter::new();
+        filter.add_stop_words("en-US");
+        filter.block("Thermos");
+        ac.set_filter(filter);
+
+        assert!(matches!(
+            ac.insert_word("thermos".to_string()).await,
+            Err(ArborError::Tokenizer(TokenizerError::Rejected {
+                reason: Rejection::Blocked,
+                ..
+            }))
+        ));
+        assert_eq!(ac.suggest_word("the").await?, ["thermal"]);
+
+        let report = ac.learn_text("the theory of thermos").await?;
+
+        assert_eq!(report.words(), ["theory"]);
+        assert_eq!(
+            report
+                .rejected
+                .iter()
+                .map(|rejected| &rejected.reason)
+                .collect::<Vec<&Rejection>>(),
+            [
+                &Rejection::StopWord,
+                &Rejection::StopWord,
+                &Rejection::Blocked
+            ]
+        );
+
+        let stats = ac.get_filter_stats();
+
+        assert_eq!((stats.stop_words, stats.blocked, stats.total()), (2, 2, 4));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_purges_words_and_keeps_incognito_words_out_of_backup() -> Result<(), Box<dyn Error>>
+    {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+
+        let mut ac =
+            Autocomplete::build(None, None, None, true, Some(&path.to_string_lossy())).await?;
+
+        ac.insert_words(Vec::from(["password".to_string(), "pasta".to_string()]))
+            .await?;
+        ac.set_incognito(true);
+        ac.insert_word("passport".to_string()).await?;
+
+        assert!(ac.is_incognito());
+        assert_eq!(std::fs::read_to_string(&path)?.lines().count(), 2);
+        assert_eq!(ac.suggest_word("pass").await?.len(), 2);
+
+        assert_eq!(ac.purge("pass*").await?, ["passport", "password"]);
+        assert_eq!(ac.suggest_word("pa").await?, ["pasta"]);
+        assert!(!std::fs::read_to_string(&path)?.contains("password"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_learns_text() -> Result<(), ArborError> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        let report = ac.learn_text("hello, world 42").await?;
+
+        assert_eq!(report.words(), Vec::from(["hello", "world"]));
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(ac.suggest_word("hel").await?, Vec::from(["hello"]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_inserts_words_in_batch() -> Result<(), ArborError> {
+        let mut ac = Autocomplete::build(None, None, None, false, None).await?;
+
+        ac.insert_words(Vec::from(["paste".to_string(), "pasta".to_string()]))
+            .await?;
+
+        let mut suggestion = ac.suggest_word("pas").await?;
+        suggestion.sort();
+
+        assert_eq!(suggestion, Vec::from(["pasta", "paste"]));
 
         Ok(())
     }
@@ -0,0 +1,393 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use fst::automaton::Str;
+use fst::{Automaton, IntoStreamer, Map, MapBuilder, Streamer};
+use memmap2::Mmap;
+
+use crate::util::compression::Compression;
+use crate::util::errors::{ArborError, StorageError, TrieError};
+
+use super::pattern::Pattern;
+use super::spellcheck::Levenshtein;
+use super::trie::Trie;
+
+/// Where a suggestion comes from. Higher layers shadow lower ones, so a word
+/// learned by the user hides the same word in a team or system dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Layer {
+    System,
+    Team,
+    Personal,
+}
+
+impl Layer {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "system" => Some(Layer::System),
+            "team" => Some(Layer::Team),
+            "personal" => Some(Layer::Personal),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Layer::System => "system",
+            Layer::Team => "team",
+            Layer::Personal => "personal",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Read-only word list compiled into a finite-state transducer, with each
+/// word's weight stored as its output value.
+pub struct Dictionary {
+    path: PathBuf,
+    map: Map<Bytes>,
+}
+
+/// A dictionary file mapped as is, or decompressed into memory.
+enum Bytes {
+    Mapped(Mmap),
+    Decompressed(Vec<u8>),
+}
+
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Bytes::Mapped(mmap) => mmap,
+            Bytes::Decompressed(bytes) => bytes,
+        }
+    }
+}
+
+impl Dictionary {
+    /// Memory-maps a dictionary file so it loads instantly and its pages are
+    /// shared between every process that opens it. Compressed dictionaries
+    /// are decompressed into memory instead.
+    pub fn open(path: &Path) -> Result<Self, ArborError> {
+        let file = File::open(path).map_err(|source| StorageError::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        // SAFETY: `compile` writes a new file and renames it over the old one,
+        // never modifying a dictionary in place, so the mapping cannot change
+        // underneath us.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|source| StorageError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let bytes = match Compression::detect(&mmap) {
+            Compression::None => Bytes::Mapped(mmap),
+            _ => Bytes::Decompressed(Compression::decompress(&mmap).map_err(|source| {
+                StorageError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            })?),
+        };
+
+        let map = Map::new(bytes).map_err(|source| StorageError::Dictionary {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            map,
+        })
+    }
+
+    /// Compiles `word` or `word<TAB>weight` lines into a dictionary file.
+    /// Duplicate words have their weights added up; a missing weight counts as 1.
+    pub fn compile(word_list: &Path, output: &Path) -> Result<usize, ArborError> {
+        Self::compile_with(word_list, output, Compression::None)
+    }
+
+    /// Compiles a dictionary like [`Dictionary::compile`], compressed for
+    /// shipping. It can't be memory-mapped, so it takes memory once opened.
+    pub fn compile_with(
+        word_list: &Path,
+        output: &Path,
+        compression: Compression,
+    ) -> Result<usize, ArborError> {
+        let contents = std::fs::read_to_string(word_list).map_err(|source| StorageError::Read {
+            path: word_list.to_path_buf(),
+            source,
+        })?;
+
+        let mut words: BTreeMap<&str, u64> = BTreeMap::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let corrupt = |source| StorageError::Corrupt {
+                path: word_list.to_path_buf(),
+                record: index + 1,
+                source,
+            };
+
+            let (word, weight) = match line.split_once('\t') {
+                Some((word, weight)) => (word, weight.trim().parse::<u64>().ok()),
+                None => (line, Some(1)),
+            };
+
+            let weight = weight.ok_or_else(|| StorageError::InvalidWeight {
+                path: word_list.to_path_buf(),
+                record: index + 1,
+                word: word.to_string(),
+            })?;
+
+            if let Some((position, character)) =
+                word.char_indices().find(|(_, c)| !Trie::is_valid_char(*c))
+            {
+                return Err(corrupt(TrieError::InvalidCharacter {
+                    word: word.to_string(),
+                    character,
+                    position,
+                })
+                .into());
+            }
+
+            *words.entry(word).or_default() += weight;
+        }
+
+        // NOTE: staged next to the output, as open dictionaries map the old file
+        let staged_path = PathBuf::from(format!("{}.tmp", output.display()));
+        let write_error = |source| StorageError::Write {
+            path: staged_path.clone(),
+            source,
+        };
+        let dictionary_error = |source| StorageError::Dictionary {
+            path: staged_path.clone(),
+            source,
+        };
+
+        let file = File::create(&staged_path).map_err(write_error)?;
+
+        if compression == Compression::None {
+            let mut builder = MapBuilder::new(BufWriter::new(file)).map_err(dictionary_error)?;
+
+            // NOTE: the BTreeMap yields words in the byte order the builder requires
+            for (word, weight) in &words {
+                builder.insert(word, *weight).map_err(dictionary_error)?;
+            }
+
+            builder.finish().map_err(dictionary_error)?;
+        } else {
+            let map = Map::from_iter(words.iter().map(|(word, weight)| (word, *weight)))
+                .map_err(dictionary_error)?;
+            let compressed = compression
+                .compress(map.as_fst().as_bytes())
+                .map_err(write_error)?;
+
+            let mut writer = BufWriter::new(file);
+
+            writer.write_all(&compressed).map_err(write_error)?;
+            writer.flush().map_err(write_error)?;
+        }
+
+        std::fs::rename(&staged_path, output).map_err(|source| StorageError::Write {
+            path: output.to_path_buf(),
+            source,
+        })?;
+
+        Ok(words.len())
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.map.contains_key(word)
+    }
+
+    pub fn get_weight(&self, word: &str) -> Option<u64> {
+        self.map.get(word)
+    }
+
+    /// Calls `f` with every word and its weight, in byte order.
+    pub fn for_each(&self, mut f: impl FnMut(&str, u64)) {
+        let mut stream = self.map.stream();
+
+        while let Some((word, weight)) = stream.next() {
+            f(&String::from_utf8_lossy(word), weight);
+        }
+    }
+
+    /// Returns up to `n` words starting with `prefix`, heaviest first.
+    pub fn suggest(&self, prefix: &str, n: usize) -> Vec<(String, u64)> {
+        let matcher = Str::new(prefix).starts_with();
+        let mut stream = self.map.search(matcher).into_stream();
+        let mut words: Vec<(String, u64)> = Vec::new();
+
+        while let Some((word, weight)) = stream.next() {
+            words.push((String::from_utf8_lossy(word).into_owned(), weight));
+
+            // NOTE: keep memory bounded on huge subtrees
+            if words.len() > n.saturating_mul(4).max(64) {
+                rank(&mut words, n);
+            }
+        }
+
+        rank(&mut words, n);
+
+        words
+    }
+
+    /// Up to `max_results` words matching `pattern`, alphabetically, and
+    /// whether more were left out.
+    pub fn find_matches(
+        &self,
+        pattern: &Pattern,
+        max_results: usize,
+    ) -> (Vec<(String, u64)>, bool) {
+        let mut stream = self.map.search(pattern).into_stream();
+        let mut words = Vec::new();
+
+        while let Some((word, weight)) = stream.next() {
+            if words.len() == max_results {
+                return (words, true);
+            }
+
+            words.push((String::from_utf8_lossy(word).into_owned(), weight));
+        }
+
+        (words, false)
+    }
+
+    /// Words within the edit distance allowed by `levenshtein`, with that
+    /// distance and their weight.
+    pub fn find_similar(&self, levenshtein: &Levenshtein) -> Vec<(String, usize, u64)> {
+        let mut stream = self.map.search_with_state(levenshtein).into_stream();
+        let mut words = Vec::new();
+
+        while let Some((word, weight, row)) = stream.next() {
+            if let Some(distance) = levenshtein.distance(&row) {
+                words.push((String::from_utf8_lossy(word).into_owned(), distance, weight));
+            }
+        }
+
+        words
+    }
+}
+
+/// The character a transducer byte stands for. Dictionary words only hold
+/// ASCII letters, so each byte is a whole character.
+pub(crate) fn byte_char(byte: u8) -> char {
+    debug_assert!(byte.is_ascii(), "dictionary words are ASCII");
+
+    byte as char
+}
+
+fn rank(words: &mut Vec<(String, u64)>, n: usize) {
+    words.sort_by(|a, b| (Reverse(a.1), &a.0).cmp(&(Reverse(b.1), &b.0)));
+    words.truncate(n);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn it_compiles_and_opens_dictionary() -> Result<(), Box<dyn Error>> {
+        let dir = std::env::temp_dir();
+        let word_list = dir.join("arbor-dictionary-words");
+        let output = dir.join("arbor-dictionary.fst");
+
+        std::fs::write(&word_list, "hello\t5\nhelp\t9\nhelium\nhello\t2\nworld\n")?;
+
+        assert_eq!(Dictionary::compile(&word_list, &output)?, 4);
+
+        let dictionary = Dictionary::open(&output)?;
+
+        assert_eq!(dictionary.len(), 4);
+        assert!(dictionary.contains("helium"));
+        assert!(!dictionary.contains("hel"));
+        assert_eq!(dictionary.get_weight("hello"), Some(7));
+        assert_eq!(
+            dictionary.suggest("hel", 2),
+            vec![("help".to_string(), 9), ("hello".to_string(), 7)]
+        );
+        assert_eq!(
+            dictionary.find_matches(&Pattern::parse("hel[lp]*")?, 1),
+            (vec![("hello".to_string(), 7)], true)
+        );
+        assert_eq!(
+            dictionary.find_similar(&Levenshtein::new("helo", 1)),
+            vec![("hello".to_string(), 1, 7), ("help".to_string(), 1, 9)]
+        );
+
+        drop(dictionary);
+
+        assert_eq!(
+            Dictionary::compile_with(&word_list, &output, Compression::Zstd)?,
+            4
+        );
+
+        let compressed = Dictionary::open(&output)?;
+
+        assert!(compressed.contains("helium"));
+        assert_eq!(compressed.suggest("hel", 1), vec![("help".to_string(), 9)]);
+
+        std::fs::remove_file(word_list)?;
+        std::fs::remove_file(output)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_invalid_word_lists() -> Result<(), Box<dyn Error>> {
+        let dir = std::env::temp_dir();
+        let word_list = dir.join("arbor-dictionary-invalid");
+        let output = dir.join("arbor-dictionary-invalid.fst");
+
+        std::fs::write(&word_list, "hello\nhello world\n")?;
+
+        let error = Dictionary::compile(&word_list, &output).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ArborError::Storage(StorageError::Corrupt { record: 2, .. })
+        ));
+
+        std::fs::write(&word_list, "hello\t5\nworld\tmany\n")?;
+
+        let error = Dictionary::compile(&word_list, &output).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ArborError::Storage(StorageError::InvalidWeight { record: 2, .. })
+        ));
+
+        std::fs::remove_file(word_list)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::util::errors::{ArborError, ConfigError, StorageError};
+
+use super::tokenizer::{self, Rejection};
+
+/// Rules every filter starts with, as `(name, pattern)`. They catch input
+/// that should never be learned even where the tokenizer would let it in,
+/// e.g. a trie that accepts digits.
+pub const RULES: [(&str, &str); 3] = [
+    ("email address", r"^[^@\s]+@[^@\s]+\.[^@\s]+$"),
+    ("card number", r"^(?:\d[ -]?){12,18}\d$"),
+    (
+        "token",
+        r"^(?:(?:sk|pk|ghp|gho|xox[abp])[_-][A-Za-z0-9_-]{10,}|[A-Fa-f0-9]{32,}|[A-Za-z0-9+/_-]{32,}={0,2})$",
+    ),
+];
+
+const STOP_WORDS_EN: [&str; 48] = [
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "for", "from", "had", "has", "have", "he", "her", "his", "if", "in",
+    "into", "is", "it", "its", "of", "on", "or", "our", "she", "so", "than", "that", "the",
+    "their", "then", "there", "they", "this", "to", "was", "we", "with",
+];
+
+const STOP_WORDS_DE: [&str; 30] = [
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "das", "dass", "dem", "den", "der",
+    "des", "die", "ein", "eine", "einen", "er", "es", "für", "im", "in", "ist", "mit", "nicht",
+    "oder", "sie", "und", "zu",
+];
+
+const STOP_WORDS_FR: [&str; 30] = [
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "est", "et", "il",
+    "je", "la", "le", "les", "leur", "mais", "ne", "nous", "ou", "par", "pas", "pour", "qui",
+    "sur", "un", "une",
+];
+
+const STOP_WORDS_ES: [&str; 30] = [
+    "al", "como", "con", "de", "del", "el", "en", "es", "esta", "ha", "la", "las", "lo", "los",
+    "mas", "me", "mi", "no", "para", "pero", "por", "que", "se", "si", "su", "sus", "un", "una",
+    "y", "ya",
+];
+
+/// The built-in stop words for a language such as `en-US`, if there are any.
+pub fn stop_words(language: &str) -> &'static [&'static str] {
+    match language.split('-').next() {
+        Some("en") => &STOP_WORDS_EN,
+        Some("de") => &STOP_WORDS_DE,
+        Some("fr") => &STOP_WORDS_FR,
+        Some("es") => &STOP_WORDS_ES,
+        _ => &[],
+    }
+}
+
+/// Words that are never learned nor suggested: stop words, blocked words,
+/// secret-looking tokens and anything matching a rule. Words are compared
+/// case-insensitively.
+pub struct WordFilter {
+    stop_words: HashSet<String>,
+    blocked: HashSet<String>,
+    rules: Vec<(String, Regex)>,
+}
+
+impl Default for WordFilter {
+    fn default() -> Self {
+        let rules = RULES
+            .iter()
+            .filter_map(|(name, pattern)| Some((name.to_string(), Regex::new(pattern).ok()?)))
+            .collect();
+
+        Self {
+            stop_words: HashSet::new(),
+            blocked: HashSet::new(),
+            rules,
+        }
+    }
+}
+
+impl WordFilter {
+    /// A filter with the built-in rules and no stop words.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the built-in stop words of `language`, returning how many there are.
+    pub fn add_stop_words(&mut self, language: &str) -> usize {
+        let words = stop_words(language);
+
+        self.stop_words
+            .extend(words.iter().map(|word| word.to_string()));
+
+        words.len()
+    }
+
+    pub fn block(&mut self, word: &str) {
+        self.blocked.insert(word.to_lowercase());
+    }
+
+    pub fn add_rule(&mut self, name: &str, pattern: &str) -> Result<(), ConfigError> {
+        let regex = Regex::new(pattern).map_err(|e| ConfigError::InvalidRule {
+            pattern: pattern.to_string(),
+            message: e.to_string(),
+        })?;
+
+        self.rules.push((name.to_string(), regex));
+
+        Ok(())
+    }
+
+    /// Reads a blocklist with one word or `/regex/` rule per line. Lines
+    /// starting with `#` are comments. Returns the number of entries read.
+    pub async fn load(&mut self, path: &Path) -> Result<usize, ArborError> {
+        let contents =
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|source| StorageError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+
+        let mut count = 0;
+
+        for line in contents.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line
+                .strip_prefix('/')
+                .and_then(|rule| rule.strip_suffix('/'))
+            {
+                Some(pattern) => self.add_rule(pattern, pattern)?,
+                None => self.block(line),
+            }
+
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Why `word` must not be learned or suggested, if it mustn't.
+    pub fn check(&self, word: &str) -> Option<Rejection> {
+        let lowercase = word.to_lowercase();
+
+        if self.blocked.contains(&lowercase) {
+            return Some(Rejection::Blocked);
+        }
+
+        if self.stop_words.contains(&lowercase) {
+            return Some(Rejection::StopWord);
+        }
+
+        if tokenizer::looks_like_secret(word) {
+            return Some(Rejection::Secret);
+        }
+
+        self.rules
+            .iter()
+            .find(|(_, regex)| regex.is_match(word))
+            .map(|(name, _)| Rejection::Rule(name.clone()))
+    }
+
+    pub fn is_filtered(&self, word: &str) -> bool {
+        self.check(word).is_some()
+    }
+}
+
+/// How many inputs were kept from being learned, by reason.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterStats {
+    pub stop_words: usize,
+    pub blocked: usize,
+    pub secrets: usize,
+    /// Matches of each rule, by rule name.
+    pub rules: BTreeMap<String, usize>,
+}
+
+impl FilterStats {
+    /// Counts `reason` if it is one of the filter's.
+    pub fn record(&mut self, reason: &Rejection) {
+        match reason {
+            Rejection::StopWord => self.stop_words += 1,
+            Rejection::Blocked => self.blocked += 1,
+            Rejection::Secret => self.secrets += 1,
+            Rejection::Rule(name) => *self.rules.entry(name.clone()).or_default() += 1,
+            _ => {}
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.stop_words + self.blocked + self.secrets + self.rules.values().sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_filters_words() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join("arbor-blocklist");
+        std::fs::write(&path, "# codenames\nBlueFalcon\n/^temp[0-9]+$/\n")?;
+
+        let mut filter = WordFilter::new();
+
+        assert_eq!(filter.add_stop_words("en-US"), 48);
+        assert_eq!(filter.load(&path).await?, 2);
+
+        assert_eq!(filter.check("The"), Some(Rejection::StopWord));
+        assert_eq!(filter.check("bluefalcon"), Some(Rejection::Blocked));
+        assert_eq!(
+            filter.check("temp42"),
+            Some(Rejection::Rule("^temp[0-9]+$".to_string()))
+        );
+        assert_eq!(
+            filter.check("jane@example.com"),
+            Some(Rejection::Rule("email address".to_string()))
+        );
+        assert_eq!(
+            filter.check("4111 1111 1111 1111"),
+            Some(Rejection::Rule("card number".to_string()))
+        );
+        assert_eq!(
+            filter.check("ghp_abcdefghijklmnop1234"),
+            Some(Rejection::Secret)
+        );
+        assert_eq!(
+            filter.check(&"c0ffee".repeat(6)),
+            Some(Rejection::Rule("token".to_string()))
+        );
+        assert_eq!(filter.check("theme"), None);
+
+        std::fs::write(&path, "/[a-/\n")?;
+
+        assert!(matches!(
+            filter.load(&path).await,
+            Err(ArborError::Config(ConfigError::InvalidRule { .. }))
+        ));
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+}
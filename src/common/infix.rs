@@ -0,0 +1,167 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem::size_of;
+use std::ops::Bound;
+
+/// Default memory budget for the index, in bytes.
+pub const DEFAULT_BUDGET: usize = 64 * 1024 * 1024;
+
+/// Finds words containing a fragment anywhere, not just at the start. Every
+/// suffix of every word is kept in sorted order, so the words containing a
+/// fragment are the ones owning a suffix that starts with it.
+pub struct InfixIndex {
+    suffixes: BTreeSet<(Box<str>, u32)>,
+    ids: HashMap<String, u32>,
+    words: Vec<Option<String>>,
+    budget: usize,
+    used: usize,
+    skipped: usize,
+}
+
+impl InfixIndex {
+    /// Creates an empty index that stops taking words once it would use more
+    /// than roughly `budget` bytes.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            suffixes: BTreeSet::new(),
+            ids: HashMap::new(),
+            words: Vec::new(),
+            budget,
+            used: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Approximate bytes taken by `word` once indexed.
+    fn cost(word: &str) -> usize {
+        let length = word.chars().count();
+        let suffix_bytes = word.len() * (length + 1) / 2;
+
+        suffix_bytes + length * size_of::<(Box<str>, u32)>() + 2 * word.len()
+    }
+
+    /// Indexes `word`. Returns false if it was already indexed or doesn't fit
+    /// in the budget, in which case it won't show up in substring searches.
+    pub fn insert(&mut self, word: &str) -> bool {
+        if self.ids.contains_key(word) {
+            return false;
+        }
+
+        let cost = Self::cost(word);
+
+        if self.used + cost > self.budget {
+            self.skipped += 1;
+            return false;
+        }
+
+        let id = self.words.len() as u32;
+
+        for (offset, _) in word.char_indices() {
+            self.suffixes.insert((word[offset..].into(), id));
+        }
+
+        self.ids.insert(word.to_string(), id);
+        self.words.push(Some(word.to_string()));
+        self.used += cost;
+
+        true
+    }
+
+    pub fn remove(&mut self, word: &str) -> bool {
+        let Some(id) = self.ids.remove(word) else {
+            return false;
+        };
+
+        for (offset, _) in word.char_indices() {
+            self.suffixes.remove(&(word[offset..].into(), id));
+        }
+
+        self.words[id as usize] = None;
+        self.used -= Self::cost(word);
+
+        true
+    }
+
+    /// Indexed words containing `fragment`, in no particular order.
+    pub fn find(&self, fragment: &str) -> Vec<&str> {
+        if fragment.is_empty() {
+            return Vec::new();
+        }
+
+        let start: (Box<str>, u32) = (fragment.into(), 0);
+        let mut ids: Vec<u32> = self
+            .suffixes
+            .range((Bound::Included(start), Bound::Unbounded))
+            .take_while(|(suffix, _)| suffix.starts_with(fragment))
+            .map(|(_, id)| *id)
+            .collect();
+
+        ids.sort_unstable();
+        ids.dedup();
+
+        ids.into_iter()
+            .filter_map(|id| self.words[id as usize].as_deref())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Approximate bytes used, never more than the budget.
+    pub fn get_memory_usage(&self) -> usize {
+        self.used
+    }
+
+    /// How many words were left out because the budget was spent.
+    pub fn get_skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_words_by_fragment() {
+        let mut index = InfixIndex::new(DEFAULT_BUDGET);
+
+        for word in ["import", "export", "portal", "sport", "report"] {
+            assert!(index.insert(word));
+        }
+
+        assert!(!index.insert("sport"));
+
+        let mut found = index.find("port");
+        found.sort();
+
+        assert_eq!(found, ["export", "import", "portal", "report", "sport"]);
+        assert_eq!(index.find("xp"), ["export"]);
+        assert!(index.find("ports").is_empty());
+
+        assert!(index.remove("export"));
+        assert!(!index.remove("export"));
+        assert!(index.find("xp").is_empty());
+        assert_eq!(index.len(), 4);
+    }
+
+    #[test]
+    fn it_respects_memory_budget() {
+        let mut index = InfixIndex::new(InfixIndex::cost("import") + InfixIndex::cost("sport"));
+
+        assert!(index.insert("import"));
+        assert!(index.insert("sport"));
+        assert!(!index.insert("report"));
+
+        assert_eq!(index.get_skipped(), 1);
+        assert!(index.get_memory_usage() <= InfixIndex::cost("import") * 2);
+
+        index.remove("import");
+
+        assert!(index.insert("report"));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::util::errors::TrieError;
+
+use super::dictionary;
+
+pub const DEFAULT_MAX_RESULTS: usize = 100;
+pub const DEFAULT_MAX_VISITED: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `?`, exactly one character.
+    Any,
+    /// `*`, any number of characters, including none.
+    Star,
+    /// `[abc]`, `[a-z]` or, negated, `[!abc]`.
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+}
+
+impl Token {
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Token::Literal(l) => *l == c,
+            Token::Any | Token::Star => true,
+            Token::Class { ranges, negated } => {
+                ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+/// A glob-style word pattern, matched one character at a time so a walk
+/// over the trie can drop a branch as soon as no state survives it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    source: String,
+    tokens: Vec<Token>,
+}
+
+/// Caps that keep pathological patterns like `*a*a*a*` bounded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PatternLimits {
+    pub max_results: usize,
+    pub max_visited: usize,
+}
+
+impl Default for PatternLimits {
+    fn default() -> Self {
+        Self {
+            max_results: DEFAULT_MAX_RESULTS,
+            max_visited: DEFAULT_MAX_VISITED,
+        }
+    }
+}
+
+/// Positions in the pattern reached so far, sorted and deduplicated.
+pub type States = Vec<usize>;
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, TrieError> {
+        let invalid = |position: usize| TrieError::InvalidPattern {
+            pattern: pattern.to_string(),
+            position,
+        };
+
+        let mut tokens = Vec::new();
+        let mut chars = pattern.char_indices().peekable();
+
+        while let Some((position, c)) = chars.next() {
+            let token = match c {
+                '?' => Token::Any,
+                '*' => {
+                    // NOTE: `**` matches the same words as `*` with more states
+                    if tokens.last() == Some(&Token::Star) {
+                        continue;
+                    }
+
+                    Token::Star
+                }
+                '[' => {
+                    let negated = chars.next_if(|(_, c)| *c == '!' || *c == '^').is_some();
+                    let mut ranges = Vec::new();
+
+                    loop {
+                        let Some((_, low)) = chars.next() else {
+                            return Err(invalid(position));
+                        };
+
+                        if low == ']' && !ranges.is_empty() {
+                            break;
+                        }
+
+                        let high = match chars.next_if(|(_, c)| *c == '-') {
+                            Some((dash, _)) => match chars.next() {
+                                Some((_, high)) if high != ']' && low <= high => high,
+                                _ => return Err(invalid(dash)),
+                            },
+                            None => low,
+                        };
+
+                        ranges.push((low, high));
+                    }
+
+                    Token::Class { ranges, negated }
+                }
+                ']' => return Err(invalid(position)),
+                c => Token::Literal(c),
+            };
+
+            tokens.push(token);
+        }
+
+        Ok(Self {
+            source: pattern.to_string(),
+            tokens,
+        })
+    }
+
+    /// Whether `text` uses any wildcard, as opposed to being a plain prefix.
+    pub fn is_pattern(text: &str) -> bool {
+        text.contains(['?', '*', '['])
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn start(&self) -> States {
+        self.closure(vec![0])
+    }
+
+    /// States reached by reading `c` from `states`; empty once nothing can match.
+    pub fn step(&self, states: &States, c: char) -> States {
+        let mut next = Vec::new();
+
+        for &state in states {
+            match self.tokens.get(state) {
+                Some(Token::Star) => next.push(state),
+                Some(token) if token.accepts(c) => next.push(state + 1),
+                _ => {}
+            }
+        }
+
+        self.closure(next)
+    }
+
+    pub fn is_match(&self, states: &States) -> bool {
+        states.contains(&self.tokens.len())
+    }
+
+    pub fn matches(&self, word: &str) -> bool {
+        let mut states = self.start();
+
+        for c in word.chars() {
+            states = self.step(&states, c);
+
+            if states.is_empty() {
+                return false;
+            }
+        }
+
+        self.is_match(&states)
+    }
+
+    /// Adds the states reachable by letting each `*` match nothing.
+    fn closure(&self, mut states: States) -> States {
+        let mut i = 0;
+
+        while i < states.len() {
+            if self.tokens.get(states[i]) == Some(&Token::Star) {
+                states.push(states[i] + 1);
+            }
+
+            i += 1;
+        }
+
+        states.sort_unstable();
+        states.dedup();
+
+        states
+    }
+}
+
+/// Lets dictionaries run the same pattern over their transducer.
+impl fst::Automaton for &Pattern {
+    type State = States;
+
+    fn start(&self) -> States {
+        Pattern::start(self)
+    }
+
+    fn is_match(&self, states: &States) -> bool {
+        Pattern::is_match(self, states)
+    }
+
+    fn can_match(&self, states: &States) -> bool {
+        !states.is_empty()
+    }
+
+    fn accept(&self, states: &States, byte: u8) -> States {
+        self.step(states, dictionary::byte_char(byte))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_wildcards() -> Result<(), TrieError> {
+        let pattern = Pattern::parse("h?ll*")?;
+
+        assert!(pattern.matches("hello"));
+        assert!(pattern.matches("hall"));
+        assert!(pattern.matches("hillside"));
+        assert!(!pattern.matches("hll"));
+        assert!(!pattern.matches("shell"));
+
+        let pattern = Pattern::parse("c[ao]t")?;
+
+        assert!(pattern.matches("cat"));
+        assert!(pattern.matches("cot"));
+        assert!(!pattern.matches("cut"));
+        assert!(!pattern.matches("cats"));
+
+        let pattern = Pattern::parse("[!a-m]*s")?;
+
+        assert!(pattern.matches("tags"));
+        assert!(pattern.matches("ss"));
+        assert!(!pattern.matches("bags"));
+
+        assert!(Pattern::parse("*a**b*")?.matches("xaybz"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_malformed_patterns() {
+        for (pattern, position) in [("c[ao", 1), ("c]t", 1), ("[z-a]", 2), ("[a-]", 2)] {
+            assert_eq!(
+                Pattern::parse(pattern).unwrap_err(),
+                TrieError::InvalidPattern {
+                    pattern: pattern.to_string(),
+                    position,
+                }
+            );
+        }
+
+        assert!(Pattern::is_pattern("h?llo"));
+        assert!(!Pattern::is_pattern("hello"));
+    }
+}
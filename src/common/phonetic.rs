@@ -0,0 +1,826 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::ops::Bound;
+
+/// Length of the codes produced by both algorithms.
+const CODE_LENGTH: usize = 4;
+
+/// How words are reduced to a code that sounds the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhoneticAlgorithm {
+    Soundex,
+    DoubleMetaphone,
+}
+
+impl PhoneticAlgorithm {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "soundex" => Some(PhoneticAlgorithm::Soundex),
+            "double-metaphone" => Some(PhoneticAlgorithm::DoubleMetaphone),
+            _ => None,
+        }
+    }
+
+    /// Both algorithms encode English pronunciation, so other languages get
+    /// no phonetic matching unless it's asked for.
+    pub fn for_language(language: &str) -> Option<Self> {
+        match language.split('-').next() {
+            Some("en") => Some(PhoneticAlgorithm::DoubleMetaphone),
+            _ => None,
+        }
+    }
+
+    /// Every code `word` is known by, most likely pronunciation first.
+    pub fn encode(&self, word: &str) -> Vec<String> {
+        match self {
+            PhoneticAlgorithm::Soundex => Vec::from([soundex(word)]),
+            PhoneticAlgorithm::DoubleMetaphone => {
+                let (primary, alternate) = double_metaphone(word);
+
+                if alternate.is_empty() || alternate == primary {
+                    Vec::from([primary])
+                } else {
+                    Vec::from([primary, alternate])
+                }
+            }
+        }
+        .into_iter()
+        .filter(|code| !code.is_empty())
+        .collect()
+    }
+}
+
+impl Display for PhoneticAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PhoneticAlgorithm::Soundex => "soundex",
+            PhoneticAlgorithm::DoubleMetaphone => "double-metaphone",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Words grouped by the codes they sound like.
+pub struct PhoneticIndex {
+    algorithm: PhoneticAlgorithm,
+    codes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl PhoneticIndex {
+    pub fn new(algorithm: PhoneticAlgorithm) -> Self {
+        Self {
+            algorithm,
+            codes: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_algorithm(&self) -> PhoneticAlgorithm {
+        self.algorithm
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        for code in self.algorithm.encode(word) {
+            self.codes.entry(code).or_default().insert(word.to_string());
+        }
+    }
+
+    pub fn remove(&mut self, word: &str) {
+        for code in self.algorithm.encode(word) {
+            if let Some(words) = self.codes.get_mut(&code) {
+                words.remove(word);
+
+                if words.is_empty() {
+                    self.codes.remove(&code);
+                }
+            }
+        }
+    }
+
+    /// Words that sound like `fragment` or like a word starting with it,
+    /// alphabetically. A partly typed word has a shorter code, so every word
+    /// whose code starts with it is a candidate.
+    pub fn find(&self, fragment: &str) -> Vec<&str> {
+        let mut words = BTreeSet::new();
+
+        for code in self.algorithm.encode(fragment) {
+            let start = Bound::Included(code.clone());
+
+            for (_, matches) in self
+                .codes
+                .range((start, Bound::Unbounded))
+                .take_while(|(candidate, _)| candidate.starts_with(&code))
+            {
+                words.extend(matches.iter().map(String::as_str));
+            }
+        }
+
+        words.into_iter().collect()
+    }
+}
+
+/// American Soundex: the first letter followed by three digits for the
+/// consonant groups that follow it.
+pub fn soundex(word: &str) -> String {
+    let digit = |c: char| match c {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    };
+
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase());
+
+    let Some(first) = letters.next() else {
+        return String::new();
+    };
+
+    let mut code = String::from(first);
+    let mut last = digit(first);
+
+    for c in letters {
+        match digit(c) {
+            Some(d) if last != Some(d) => {
+                code.push(d);
+                last = Some(d);
+            }
+            Some(_) => {}
+            // NOTE: H and W don't separate two letters with the same digit
+            None if c == 'H' || c == 'W' => {}
+            None => last = None,
+        }
+
+        if code.len() == CODE_LENGTH {
+            break;
+        }
+    }
+
+    while code.len() < CODE_LENGTH {
+        code.push('0');
+    }
+
+    code
+}
+
+/// Lawrence Philips' Double Metaphone. Returns the primary code and an
+/// alternate one for words with a second common pronunciation, which is
+/// empty when there is none.
+pub fn double_metaphone(word: &str) -> (String, String) {
+    let mut encoder = Metaphone::new(word);
+
+    encoder.run();
+
+    let truncate = |code: String| code.chars().take(CODE_LENGTH).collect::<String>();
+
+    (truncate(encoder.primary), truncate(encoder.alternate))
+}
+
+struct Metaphone {
+    word: Vec<char>,
+    primary: String,
+    alternate: String,
+    slavo_germanic: bool,
+}
+
+impl Metaphone {
+    fn new(word: &str) -> Self {
+        let word: Vec<char> = word.to_uppercase().chars().collect();
+        let text: String = word.iter().collect();
+        let slavo_germanic = text.contains('W')
+            || text.contains('K')
+            || text.contains("CZ")
+            || text.contains("WITZ");
+
+        Self {
+            word,
+            primary: String::new(),
+            alternate: String::new(),
+            slavo_germanic,
+        }
+    }
+
+    fn len(&self) -> isize {
+        self.word.len() as isize
+    }
+
+    fn char_at(&self, i: isize) -> Option<char> {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| self.word.get(i).copied())
+    }
+
+    /// Whether any of `options` appears at position `start`.
+    fn at(&self, start: isize, options: &[&str]) -> bool {
+        let Ok(start) = usize::try_from(start) else {
+            return false;
+        };
+
+        options.iter().any(|option| {
+            let option: Vec<char> = option.chars().collect();
+
+            self.word.get(start..start + option.len()) == Some(&option[..])
+        })
+    }
+
+    fn is_vowel(&self, i: isize) -> bool {
+        self.char_at(i).is_some_and(|c| "AEIOUY".contains(c))
+    }
+
+    fn add(&mut self, primary: &str, alternate: &str) {
+        self.primary.push_str(primary);
+        self.alternate.push_str(alternate);
+    }
+
+    fn add_both(&mut self, code: &str) {
+        self.add(code, code);
+    }
+
+    fn run(&mut self) {
+        let last = self.len() - 1;
+        let mut i: isize = 0;
+
+        if self.at(0, &["GN", "KN", "PN", "WR", "PS"]) {
+            i = 1;
+        }
+
+        if self.at(0, &["X"]) {
+            self.add_both("S");
+            i = 1;
+        }
+
+        while i <= last && (self.primary.len() < CODE_LENGTH || self.alternate.len() < CODE_LENGTH)
+        {
+            let Some(c) = self.char_at(i) else {
+                break;
+            };
+
+            i += match c {
+                'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                    if i == 0 {
+                        self.add_both("A");
+                    }
+
+                    1
+                }
+                'B' => {
+                    self.add_both("P");
+
+                    if self.at(i + 1, &["B"]) {
+                        2
+                    } else {
+                        1
+                    }
+                }
+                'Ç' => {
+                    self.add_both("S");
+                    1
+                }
+                'C' => self.encode_c(i),
+                'D' => {
+                    if self.at(i, &["DG"]) {
+                        if self.at(i + 2, &["I", "E", "Y"]) {
+                            self.add_both("J");
+                            3
+                        } else {
+                            self.add_both("TK");
+                            2
+                        }
+                    } else if self.at(i, &["DT", "DD"]) {
+                        self.add_both("T");
+                        2
+                    } else {
+                        self.add_both("T");
+                        1
+                    }
+                }
+                'G' => self.encode_g(i),
+                'H' if (i == 0 || self.is_vowel(i - 1)) && self.is_vowel(i + 1) => {
+                    self.add_both("H");
+                    2
+                }
+                'J' => self.encode_j(i),
+                'L' => {
+                    if self.at(i + 1, &["L"]) {
+                        let spanish = (i == self.len() - 3
+                            && self.at(i - 1, &["ILLO", "ILLA", "ALLE"]))
+                            || ((self.at(last - 1, &["AS", "OS"]) || self.at(last, &["A", "O"]))
+                                && self.at(i - 1, &["ALLE"]));
+
+                        if spanish {
+                            self.add("L", "");
+                        } else {
+                            self.add_both("L");
+                        }
+
+                        2
+                    } else {
+                        self.add_both("L");
+                        1
+                    }
+                }
+                'M' => {
+                    self.add_both("M");
+
+                    if (self.at(i - 1, &["UMB"]) && (i + 1 == last || self.at(i + 2, &["ER"])))
+                        || self.at(i + 1, &["M"])
+                    {
+                        2
+                    } else {
+                        1
+                    }
+                }
+                'Ñ' => {
+                    self.add_both("N");
+                    1
+                }
+                'P' => {
+                    if self.at(i + 1, &["H"]) {
+                        self.add_both("F");
+                        2
+                    } else {
+                        self.add_both("P");
+
+                        if self.at(i + 1, &["P", "B"]) {
+                            2
+                        } else {
+                            1
+                        }
+                    }
+                }
+                'R' => {
+                    if i == last
+                        && !self.slavo_germanic
+                        && self.at(i - 2, &["IE"])
+                        && !self.at(i - 4, &["ME", "MA"])
+                    {
+                        self.add("", "R");
+                    } else {
+                        self.add_both("R");
+                    }
+
+                    if self.at(i + 1, &["R"]) {
+                        2
+                    } else {
+                        1
+                    }
+                }
+                'S' => self.encode_s(i),
+                'T' => self.encode_t(i),
+                'W' => self.encode_w(i),
+                'X' => {
+                    let silent = i == last
+                        && (self.at(i - 3, &["IAU", "EAU"]) || self.at(i - 2, &["AU", "OU"]));
+
+                    if !silent {
+                        self.add_both("KS");
+                    }
+
+                    if self.at(i + 1, &["C", "X"]) {
+                        2
+                    } else {
+                        1
+                    }
+                }
+                'Z' => {
+                    if self.at(i + 1, &["H"]) {
+                        self.add_both("J");
+                        2
+                    } else {
+                        if self.at(i + 1, &["ZO", "ZI", "ZA"])
+                            || (self.slavo_germanic && i > 0 && !self.at(i - 1, &["T"]))
+                        {
+                            self.add("S", "TS");
+                        } else {
+                            self.add_both("S");
+                        }
+
+                        if self.at(i + 1, &["Z"]) {
+                            2
+                        } else {
+                            1
+                        }
+                    }
+                }
+                'F' | 'K' | 'N' | 'Q' | 'V' => {
+                    let code = match c {
+                        'F' | 'V' => "F",
+                        'K' | 'Q' => "K",
+                        _ => "N",
+                    };
+
+                    self.add_both(code);
+
+                    if self.char_at(i + 1) == Some(c) {
+                        2
+                    } else {
+                        1
+                    }
+                }
+                _ => 1,
+            };
+        }
+    }
+
+    fn encode_c(&mut self, i: isize) -> isize {
+        let germanic_ch = i > 1
+            && !self.is_vowel(i - 2)
+            && self.at(i - 1, &["ACH"])
+            && !self.at(i + 2, &["I"])
+            && (!self.at(i + 2, &["E"]) || self.at(i - 2, &["BACHER", "MACHER"]));
+
+        if germanic_ch {
+            self.add_both("K");
+            return 2;
+        }
+
+        if i == 0 && self.at(i, &["CAESAR"]) {
+            self.add_both("S");
+            return 2;
+        }
+
+        if self.at(i, &["CHIA"]) {
+            self.add_both("K");
+            return 2;
+        }
+
+        if self.at(i, &["CH"]) {
+            if i > 0 && self.at(i, &["CHAE"]) {
+                self.add("K", "X");
+            } else if (i == 0
+                && self.at(i + 1, &["HARAC", "HARIS", "HOR", "HYM", "HIA", "HEM"])
+                && !self.at(0, &["CHORE"]))
+                || self.at(0, &["VAN ", "VON ", "SCH"])
+                || self.at(i - 2, &["ORCHES", "ARCHIT", "ORCHID"])
+                || self.at(i + 2, &["T", "S"])
+                || ((i == 0 || self.at(i - 1, &["A", "O", "U", "E"]))
+                    && self.at(i + 2, &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "]))
+            {
+                self.add_both("K");
+            } else if i > 0 {
+                if self.at(0, &["MC"]) {
+                    self.add_both("K");
+                } else {
+                    self.add("X", "K");
+                }
+            } else {
+                self.add_both("X");
+            }
+
+            return 2;
+        }
+
+        if self.at(i, &["CZ"]) && !self.at(i - 2, &["WICZ"]) {
+            self.add("S", "X");
+            return 2;
+        }
+
+        if self.at(i + 1, &["CIA"]) {
+            self.add_both("X");
+            return 3;
+        }
+
+        if self.at(i, &["CC"]) && !(i == 1 && self.at(0, &["M"])) {
+            if self.at(i + 2, &["I", "E", "H"]) && !self.at(i + 2, &["HU"]) {
+                if (i == 1 && self.at(i - 1, &["A"])) || self.at(i - 1, &["UCCEE", "UCCES"]) {
+                    self.add_both("KS");
+                } else {
+                    self.add_both("X");
+                }
+
+                return 3;
+            }
+
+            self.add_both("K");
+            return 2;
+        }
+
+        if self.at(i, &["CK", "CG", "CQ"]) {
+            self.add_both("K");
+            return 2;
+        }
+
+        if self.at(i, &["CI", "CE", "CY"]) {
+            if self.at(i, &["CIO", "CIE", "CIA"]) {
+                self.add("S", "X");
+            } else {
+                self.add_both("S");
+            }
+
+            return 2;
+        }
+
+        self.add_both("K");
+
+        if self.at(i + 1, &[" C", " Q", " G"]) {
+            3
+        } else if self.at(i + 1, &["C", "K", "Q"]) && !self.at(i + 1, &["CE", "CI"]) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn encode_g(&mut self, i: isize) -> isize {
+        if self.at(i + 1, &["H"]) {
+            if i > 0 && !self.is_vowel(i - 1) {
+                self.add_both("K");
+            } else if i == 0 {
+                if self.at(i + 2, &["I"]) {
+                    self.add_both("J");
+                } else {
+                    self.add_both("K");
+                }
+            } else if (i > 1 && self.at(i - 2, &["B", "H", "D"]))
+                || (i > 2 && self.at(i - 3, &["B", "H", "D"]))
+                || (i > 3 && self.at(i - 4, &["B", "H"]))
+            {
+                // NOTE: silent, as in "bough" or "daughter"
+            } else if i > 2 && self.at(i - 1, &["U"]) && self.at(i - 3, &["C", "G", "L", "R", "T"])
+            {
+                self.add_both("F");
+            } else if i > 0 && !self.at(i - 1, &["I"]) {
+                self.add_both("K");
+            }
+
+            return 2;
+        }
+
+        if self.at(i + 1, &["N"]) {
+            if i == 1 && self.is_vowel(0) && !self.slavo_germanic {
+                self.add("KN", "N");
+            } else if !self.at(i + 2, &["EY"]) && !self.at(i + 1, &["Y"]) && !self.slavo_germanic {
+                self.add("N", "KN");
+            } else {
+                self.add_both("KN");
+            }
+
+            return 2;
+        }
+
+        if self.at(i + 1, &["LI"]) && !self.slavo_germanic {
+            self.add("KL", "L");
+            return 2;
+        }
+
+        if i == 0
+            && (self.at(i + 1, &["Y"])
+                || self.at(
+                    i + 1,
+                    &[
+                        "ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER",
+                    ],
+                ))
+        {
+            self.add("K", "J");
+            return 2;
+        }
+
+        if (self.at(i + 1, &["ER"]) || self.at(i + 1, &["Y"]))
+            && !self.at(0, &["DANGER", "RANGER", "MANGER"])
+            && !self.at(i - 1, &["E", "I"])
+            && !self.at(i - 1, &["RGY", "OGY"])
+        {
+            self.add("K", "J");
+            return 2;
+        }
+
+        if self.at(i + 1, &["E", "I", "Y"]) || self.at(i - 1, &["AGGI", "OGGI"]) {
+            if self.at(0, &["VAN ", "VON ", "SCH"]) || self.at(i + 1, &["ET"]) {
+                self.add_both("K");
+            } else if self.at(i + 1, &["IER "]) {
+                self.add_both("J");
+            } else {
+                self.add("J", "K");
+            }
+
+            return 2;
+        }
+
+        self.add_both("K");
+
+        if self.at(i + 1, &["G"]) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn encode_j(&mut self, i: isize) -> isize {
+        if self.at(i, &["JOSE"]) || self.at(0, &["SAN "]) {
+            if (i == 0 && self.at(i + 4, &[" "])) || self.at(0, &["SAN "]) {
+                self.add_both("H");
+            } else {
+                self.add("J", "H");
+            }
+
+            return 1;
+        }
+
+        if i == 0 {
+            self.add("J", "A");
+        } else if self.is_vowel(i - 1) && !self.slavo_germanic && self.at(i + 1, &["A", "O"]) {
+            self.add("J", "H");
+        } else if i == self.len() - 1 {
+            self.add("J", "");
+        } else if !self.at(i + 1, &["L", "T", "K", "S", "N", "M", "B", "Z"])
+            && !self.at(i - 1, &["S", "K", "L"])
+        {
+            self.add_both("J");
+        }
+
+        if self.at(i + 1, &["J"]) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn encode_s(&mut self, i: isize) -> isize {
+        if self.at(i - 1, &["ISL", "YSL"]) {
+            return 1;
+        }
+
+        if i == 0 && self.at(i, &["SUGAR"]) {
+            self.add("X", "S");
+            return 1;
+        }
+
+        if self.at(i, &["SH"]) {
+            if self.at(i + 1, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+                self.add_both("S");
+            } else {
+                self.add_both("X");
+            }
+
+            return 2;
+        }
+
+        if self.at(i, &["SIO", "SIA"]) {
+            if self.slavo_germanic {
+                self.add_both("S");
+            } else {
+                self.add("S", "X");
+            }
+
+            return 3;
+        }
+
+        if (i == 0 && self.at(i + 1, &["M", "N", "L", "W"])) || self.at(i + 1, &["Z"]) {
+            self.add("S", "X");
+
+            return if self.at(i + 1, &["Z"]) { 2 } else { 1 };
+        }
+
+        if self.at(i, &["SC"]) {
+            if self.at(i + 2, &["H"]) {
+                if self.at(i + 3, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                    if self.at(i + 3, &["ER", "EN"]) {
+                        self.add("X", "SK");
+                    } else {
+                        self.add_both("SK");
+                    }
+                } else if i == 0 && !self.is_vowel(3) && !self.at(3, &["W"]) {
+                    self.add("X", "S");
+                } else {
+                    self.add_both("X");
+                }
+            } else if self.at(i + 2, &["I", "E", "Y"]) {
+                self.add_both("S");
+            } else {
+                self.add_both("SK");
+            }
+
+            return 3;
+        }
+
+        if i == self.len() - 1 && self.at(i - 2, &["AI", "OI"]) {
+            self.add("", "S");
+        } else {
+            self.add_both("S");
+        }
+
+        if self.at(i + 1, &["S", "Z"]) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn encode_t(&mut self, i: isize) -> isize {
+        if self.at(i, &["TION", "TIA", "TCH"]) {
+            self.add_both("X");
+            return 3;
+        }
+
+        if self.at(i, &["TH", "TTH"]) {
+            if self.at(i + 2, &["OM", "AM"]) || self.at(0, &["VAN ", "VON ", "SCH"]) {
+                self.add_both("T");
+            } else {
+                self.add("0", "T");
+            }
+
+            return 2;
+        }
+
+        self.add_both("T");
+
+        if self.at(i + 1, &["T", "D"]) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn encode_w(&mut self, i: isize) -> isize {
+        if self.at(i, &["WR"]) {
+            self.add_both("R");
+            return 2;
+        }
+
+        if i == 0 && (self.is_vowel(i + 1) || self.at(i, &["WH"])) {
+            if self.is_vowel(i + 1) {
+                self.add("A", "F");
+            } else {
+                self.add_both("A");
+            }
+        }
+
+        if (i == self.len() - 1 && self.is_vowel(i - 1))
+            || self.at(i - 1, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+            || self.at(0, &["SCH"])
+        {
+            self.add("", "F");
+            return 1;
+        }
+
+        if self.at(i, &["WICZ", "WITZ"]) {
+            self.add("TS", "FX");
+            return 4;
+        }
+
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_soundex() {
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Lee"), "L000");
+        assert_eq!(soundex(""), "");
+    }
+
+    #[test]
+    fn it_encodes_double_metaphone() {
+        for (word, primary, alternate) in [
+            ("Philip", "FLP", "FLP"),
+            ("Fillip", "FLP", "FLP"),
+            ("Schmidt", "XMT", "SMT"),
+            ("Knight", "NT", "NT"),
+            ("Jose", "JS", "HS"),
+            ("Smith", "SM0", "XMT"),
+            ("Xavier", "SF", "SFR"),
+            ("Caesar", "SSR", "SSR"),
+        ] {
+            assert_eq!(
+                double_metaphone(word),
+                (primary.to_string(), alternate.to_string()),
+                "{}",
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn it_finds_words_that_sound_alike() {
+        let mut index = PhoneticIndex::new(PhoneticAlgorithm::DoubleMetaphone);
+
+        for word in ["Philip", "Phillips", "Fiona", "Smith", "Schmidt"] {
+            index.insert(word);
+        }
+
+        assert_eq!(index.find("Fillip"), ["Philip", "Phillips"]);
+        assert_eq!(index.find("Smyth"), ["Schmidt", "Smith"]);
+
+        index.remove("Phillips");
+
+        assert_eq!(index.find("Fil"), ["Philip"]);
+        assert_eq!(
+            PhoneticAlgorithm::for_language("en-GB"),
+            Some(PhoneticAlgorithm::DoubleMetaphone)
+        );
+        assert_eq!(PhoneticAlgorithm::for_language("tr-TR"), None);
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::util::errors::RankerError;
+
+use super::dictionary::Layer;
+
+/// Names accepted by `parse`, in the order they are documented.
+pub const RANKERS: [&str; 7] = [
+    "lexicographic",
+    "shortest",
+    "frequency",
+    "recency",
+    "frequency-recency",
+    "context",
+    "stem",
+];
+
+/// A word competing for a place in the suggestion list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<'a> {
+    pub word: &'a str,
+    pub frequency: u32,
+    /// Seconds since the Unix epoch, `None` for words without usage history.
+    pub last_used: Option<u64>,
+    pub layer: Layer,
+    /// How often any word with the same stem was used, at least `frequency`.
+    pub stem_frequency: u32,
+}
+
+/// What a ranker may take into account besides the candidate itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context<'a> {
+    pub now: u64,
+    pub half_life: Duration,
+    /// The word typed before the one being completed, if any.
+    pub previous: Option<&'a str>,
+}
+
+/// How the trie orders the words it caches under each prefix, which decides
+/// what `Ranker::upper_bound` can promise about the words left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOrder {
+    /// Most frequent first.
+    Frequency,
+    /// Highest frequency decayed by the half-life first, as
+    /// `FrequencyRecency` scores them.
+    Decayed,
+}
+
+/// Scores candidates for `Autocomplete`; higher scores are suggested first
+/// and ties are broken alphabetically.
+pub trait Ranker: Send + Sync {
+    fn score(&self, candidate: &Candidate, context: &Context) -> f64;
+
+    /// The order `upper_bound` works best with.
+    fn cache_order(&self) -> CacheOrder {
+        CacheOrder::Frequency
+    }
+
+    /// The highest score any word the trie ranked below `lowest` could get,
+    /// given the order it caches words in. Knowing it lets `Autocomplete`
+    /// stop at the cached words instead of scoring the whole subtree.
+    fn upper_bound(
+        &self,
+        _lowest: &Candidate,
+        _order: CacheOrder,
+        _context: &Context,
+    ) -> Option<f64> {
+        None
+    }
+
+    /// Called with every sequence of words the user commits, in order.
+    fn learn(&mut self, _words: &[String]) {}
+}
+
+/// Ranks alphabetically.
+pub struct Lexicographic;
+
+impl Ranker for Lexicographic {
+    fn score(&self, _candidate: &Candidate, _context: &Context) -> f64 {
+        0.0
+    }
+}
+
+/// Ranks shorter words first, so completions save the fewest keystrokes.
+pub struct Shortest;
+
+impl Ranker for Shortest {
+    fn score(&self, candidate: &Candidate, _context: &Context) -> f64 {
+        -(candidate.word.chars().count() as f64)
+    }
+}
+
+/// Ranks by how often a word was used.
+pub struct Frequency;
+
+impl Ranker for Frequency {
+    fn score(&self, candidate: &Candidate, _context: &Context) -> f64 {
+        candidate.frequency as f64
+    }
+
+    fn upper_bound(
+        &self,
+        lowest: &Candidate,
+        order: CacheOrder,
+        _context: &Context,
+    ) -> Option<f64> {
+        match order {
+            CacheOrder::Frequency => Some(lowest.frequency as f64),
+            CacheOrder::Decayed => None,
+        }
+    }
+}
+
+/// Ranks by how recently a word was used, regardless of how often.
+pub struct Recency;
+
+impl Ranker for Recency {
+    fn score(&self, candidate: &Candidate, context: &Context) -> f64 {
+        match candidate.last_used {
+            Some(_) => decay(candidate.last_used, context),
+            None => 0.0,
+        }
+    }
+}
+
+/// Ranks by frequency, halving a word's weight every `half_life` it goes unused.
+pub struct FrequencyRecency;
+
+impl Ranker for FrequencyRecency {
+    fn score(&self, candidate: &Candidate, context: &Context) -> f64 {
+        candidate.frequency as f64 * decay(candidate.last_used, context)
+    }
+
+    fn cache_order(&self) -> CacheOrder {
+        CacheOrder::Decayed
+    }
+
+    fn upper_bound(&self, lowest: &Candidate, order: CacheOrder, context: &Context) -> Option<f64> {
+        match order {
+            CacheOrder::Frequency => Some(lowest.frequency as f64),
+            // NOTE: untouched words sort first whatever their frequency
+            CacheOrder::Decayed => lowest.last_used.map(|_| self.score(lowest, context)),
+        }
+    }
+}
+
+/// Ranks by how often a word followed the previous word.
+#[derive(Default)]
+pub struct Bigram {
+    counts: HashMap<String, HashMap<String, u32>>,
+}
+
+impl Ranker for Bigram {
+    fn score(&self, candidate: &Candidate, context: &Context) -> f64 {
+        context
+            .previous
+            .and_then(|previous| self.counts.get(previous))
+            .and_then(|next| next.get(candidate.word))
+            .map_or(0.0, |count| *count as f64)
+    }
+
+    fn learn(&mut self, words: &[String]) {
+        for pair in words.windows(2) {
+            *self
+                .counts
+                .entry(pair[0].clone())
+                .or_default()
+                .entry(pair[1].clone())
+                .or_default() += 1;
+        }
+    }
+}
+
+/// Ranks by how often words sharing a stem were used, so "runs" rises
+/// with every use of "running".
+pub struct StemFrequency;
+
+impl Ranker for StemFrequency {
+    fn score(&self, candidate: &Candidate, _context: &Context) -> f64 {
+        candidate.stem_frequency as f64
+    }
+}
+
+/// Sums the scores of several rankers, each scaled by its weight.
+pub struct Weighted {
+    rankers: Vec<(f64, Box<dyn Ranker>)>,
+}
+
+impl Weighted {
+    pub fn new(rankers: Vec<(f64, Box<dyn Ranker>)>) -> Self {
+        Self { rankers }
+    }
+}
+
+impl Ranker for Weighted {
+    fn score(&self, candidate: &Candidate, context: &Context) -> f64 {
+        self.rankers
+            .iter()
+            .map(|(weight, ranker)| weight * ranker.score(candidate, context))
+            .sum()
+    }
+
+    fn cache_order(&self) -> CacheOrder {
+        let decayed = self
+            .rankers
+            .iter()
+            .all(|(_, ranker)| ranker.cache_order() == CacheOrder::Decayed);
+
+        if decayed {
+            CacheOrder::Decayed
+        } else {
+            CacheOrder::Frequency
+        }
+    }
+
+    fn upper_bound(&self, lowest: &Candidate, order: CacheOrder, context: &Context) -> Option<f64> {
+        self.rankers
+            .iter()
+            .map(|(weight, ranker)| Some(weight * ranker.upper_bound(lowest, order, context)?))
+            .sum()
+    }
+
+    fn learn(&mut self, words: &[String]) {
+        for (_, ranker) in &mut self.rankers {
+            ranker.learn(words);
+        }
+    }
+}
+
+pub fn preset(name: &str) -> Result<Box<dyn Ranker>, RankerError> {
+    Ok(match name {
+        "lexicographic" => Box::new(Lexicographic),
+        "shortest" => Box::new(Shortest),
+        "frequency" => Box::new(Frequency),
+        "recency" => Box::new(Recency),
+        "frequency-recency" => Box::new(FrequencyRecency),
+        "context" => Box::new(Bigram::default()),
+        "stem" => Box::new(StemFrequency),
+        _ => return Err(RankerError::UnknownRanker(name.to_string())),
+    })
+}
+
+/// Parses a ranker written as a single name, e.g. `frequency`, or as weighted
+/// names joined by commas, e.g. `frequency-recency=1,context=2`.
+pub fn parse(spec: &str) -> Result<Box<dyn Ranker>, RankerError> {
+    let spec = spec.trim();
+
+    if !spec.contains(['=', ',']) {
+        return preset(spec);
+    }
+
+    let mut rankers = Vec::new();
+
+    for part in spec.split(',') {
+        let (name, weight) = match part.split_once('=') {
+            Some((name, weight)) => {
+                let weight = weight
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|weight| weight.is_finite() && *weight >= 0.0)
+                    .ok_or_else(|| RankerError::InvalidWeight(weight.trim().to_string()))?;
+
+                (name, weight)
+            }
+            None => (part, 1.0),
+        };
+
+        rankers.push((weight, preset(name.trim())?));
+    }
+
+    Ok(Box::new(Weighted::new(rankers)))
+}
+
+/// Exponential decay factor in `(0, 1]`. Words without usage history, such
+/// as dictionary words, don't decay.
+pub fn decay(last_used: Option<u64>, context: &Context) -> f64 {
+    let Some(last_used) = last_used else {
+        return 1.0;
+    };
+
+    let half_life = context.half_life.as_secs_f64();
+
+    if half_life <= 0.0 {
+        return 1.0;
+    }
+
+    let age = context.now.saturating_sub(last_used) as f64;
+
+    0.5_f64.powf(age / half_life)
+}
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn candidate(word: &str, frequency: u32, last_used: Option<u64>) -> Candidate<'_> {
+        Candidate {
+            word,
+            frequency,
+            last_used,
+            layer: Layer::Personal,
+            stem_frequency: frequency,
+        }
+    }
+
+    fn context(previous: Option<&str>) -> Context<'_> {
+        Context {
+            now: 100 * DAY,
+            half_life: Duration::from_secs(7 * DAY),
+            previous,
+        }
+    }
+
+    /// Orders `candidates` with `ranker` the way `Autocomplete` does.
+    fn rank(ranker: &dyn Ranker, candidates: &[Candidate], context: &Context) -> Vec<String> {
+        let mut scored: Vec<(f64, &str)> = candidates
+            .iter()
+            .map(|candidate| (ranker.score(candidate, context), candidate.word))
+            .collect();
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        scored
+            .into_iter()
+            .map(|(_, word)| word.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_decays_by_half_life() {
+        let context = context(None);
+
+        assert_eq!(decay(Some(100 * DAY), &context), 1.0);
+        assert_eq!(decay(Some(93 * DAY), &context), 0.5);
+        assert_eq!(decay(Some(86 * DAY), &context), 0.25);
+        assert_eq!(decay(None, &context), 1.0);
+    }
+
+    #[test]
+    fn it_prefers_recent_words() {
+        let context = context(None);
+        let stale = candidate("stale", 10, Some(72 * DAY));
+        let fresh = candidate("fresh", 2, Some(100 * DAY));
+
+        assert!(Frequency.score(&stale, &context) > Frequency.score(&fresh, &context));
+        assert_eq!(FrequencyRecency.score(&stale, &context), 10.0 / 16.0);
+        assert_eq!(FrequencyRecency.score(&fresh, &context), 2.0);
+    }
+
+    #[test]
+    fn it_bounds_decayed_scores() {
+        let context = context(None);
+        let order = FrequencyRecency.cache_order();
+
+        assert_eq!(order, CacheOrder::Decayed);
+        assert_eq!(
+            FrequencyRecency.upper_bound(&candidate("stale", 10, Some(86 * DAY)), order, &context),
+            Some(2.5)
+        );
+        assert_eq!(
+            FrequencyRecency.upper_bound(&candidate("helm", 1, None), order, &context),
+            None
+        );
+        assert_eq!(
+            Frequency.upper_bound(&candidate("stale", 10, None), order, &context),
+            None
+        );
+    }
+
+    #[test]
+    fn it_ranks_with_each_preset() -> Result<(), RankerError> {
+        let context = context(None);
+        let candidates = [
+            candidate("helicopter", 9, Some(60 * DAY)),
+            candidate("hello", 3, Some(100 * DAY)),
+            candidate("help", 3, Some(93 * DAY)),
+            candidate("helm", 1, None),
+        ];
+
+        let expected = [
+            ("lexicographic", ["helicopter", "hello", "helm", "help"]),
+            ("shortest", ["helm", "help", "hello", "helicopter"]),
+            ("frequency", ["helicopter", "hello", "help", "helm"]),
+            ("recency", ["hello", "help", "helicopter", "helm"]),
+            ("frequency-recency", ["hello", "help", "helm", "helicopter"]),
+        ];
+
+        for (name, words) in expected {
+            assert_eq!(rank(preset(name)?.as_ref(), &candidates, &context), words);
+        }
+
+        assert!(matches!(
+            preset("random"),
+            Err(RankerError::UnknownRanker(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_ranks_by_previous_word() {
+        let mut bigram = Bigram::default();
+
+        bigram.learn(&["good".to_string(), "morning".to_string()]);
+        bigram.learn(&["good".to_string(), "movie".to_string()]);
+        bigram.learn(&["good".to_string(), "movie".to_string()]);
+
+        let candidates = [
+            candidate("morning", 5, None),
+            candidate("movie", 1, None),
+            candidate("mother", 2, None),
+        ];
+
+        assert_eq!(
+            rank(&bigram, &candidates, &context(Some("good"))),
+            ["movie", "morning", "mother"]
+        );
+        assert_eq!(
+            rank(&bigram, &candidates, &context(None)),
+            ["morning", "mother", "movie"]
+        );
+    }
+
+    #[test]
+    fn it_boosts_words_by_stem() -> Result<(), RankerError> {
+        let context = context(None);
+        let runs = Candidate {
+            stem_frequency: 9,
+            ..candidate("runs", 1, None)
+        };
+        let rust = candidate("rust", 4, None);
+
+        assert_eq!(
+            rank(&StemFrequency, &[rust.clone(), runs.clone()], &context),
+            ["runs", "rust"]
+        );
+        assert_eq!(
+            rank(
+                parse("frequency=1,stem=0.25")?.as_ref(),
+                &[rust, runs],
+                &context
+            ),
+            ["rust", "runs"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_combines_weighted_rankers() -> Result<(), RankerError> {
+        let mut ranker = parse("frequency=1, context=10")?;
+
+        ranker.learn(&["good".to_string(), "movie".to_string()]);
+
+        let candidates = [candidate("morning", 5, None), candidate("movie", 1, None)];
+
+        assert_eq!(
+            rank(ranker.as_ref(), &candidates, &context(Some("good"))),
+            ["movie", "morning"]
+        );
+        assert_eq!(
+            rank(ranker.as_ref(), &candidates, &context(None)),
+            ["morning", "movie"]
+        );
+        let lowest = candidate("movie", 4, Some(93 * DAY));
+        let order = CacheOrder::Frequency;
+
+        assert_eq!(ranker.upper_bound(&lowest, order, &context(None)), None);
+
+        let ranker = parse("frequency=2,frequency-recency")?;
+
+        assert_eq!(ranker.cache_order(), order);
+        assert_eq!(
+            ranker.upper_bound(&lowest, order, &context(None)),
+            Some(12.0)
+        );
+        assert!(matches!(
+            parse("frequency=-1"),
+            Err(RankerError::InvalidWeight(_))
+        ));
+
+        Ok(())
+    }
+}
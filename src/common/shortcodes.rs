@@ -0,0 +1,436 @@
+use std::fmt::Display;
+
+use super::trie::Trie;
+
+/// A built-in table of shortcodes and the glyphs they stand for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShortcodeSet {
+    Emoji,
+    Latex,
+    Html,
+}
+
+impl ShortcodeSet {
+    pub const ALL: [ShortcodeSet; 3] =
+        [ShortcodeSet::Emoji, ShortcodeSet::Latex, ShortcodeSet::Html];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "emoji" => Some(ShortcodeSet::Emoji),
+            "latex" => Some(ShortcodeSet::Latex),
+            "html" => Some(ShortcodeSet::Html),
+            _ => None,
+        }
+    }
+
+    /// `:smile`, `\alpha` and `&amp` by default.
+    pub fn default_sigil(&self) -> char {
+        match self {
+            ShortcodeSet::Emoji => ':',
+            ShortcodeSet::Latex => '\\',
+            ShortcodeSet::Html => '&',
+        }
+    }
+
+    pub fn entries(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            ShortcodeSet::Emoji => EMOJI,
+            ShortcodeSet::Latex => LATEX,
+            ShortcodeSet::Html => HTML,
+        }
+    }
+}
+
+impl Display for ShortcodeSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ShortcodeSet::Emoji => "emoji",
+            ShortcodeSet::Latex => "latex",
+            ShortcodeSet::Html => "html",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Shortcodes keyed by their sigil and name, e.g. `:smile`, in a trie of
+/// their own since they're made of characters words never contain.
+pub struct Shortcodes {
+    trie: Trie<&'static str>,
+    sigils: Vec<char>,
+}
+
+impl Shortcodes {
+    /// Loads each set under its sigil. Sets may share a sigil.
+    pub fn new(sets: &[(ShortcodeSet, char)]) -> Self {
+        let mut trie = Trie::with_valid_chars(|c| !c.is_whitespace());
+
+        for (set, sigil) in sets {
+            for (name, glyph) in set.entries() {
+                // NOTE: every built-in name is free of whitespace
+                let _ = trie.insert_with_value(&format!("{}{}", sigil, name), *glyph);
+            }
+        }
+
+        Self {
+            trie,
+            sigils: sets.iter().map(|(_, sigil)| *sigil).collect(),
+        }
+    }
+
+    /// Whether `word` starts with a sigil and at least one more character,
+    /// so a lone `:` or `&` is left alone.
+    pub fn is_shortcode(&self, word: &str) -> bool {
+        let mut chars = word.chars();
+
+        chars.next().is_some_and(|c| self.sigils.contains(&c)) && chars.next().is_some()
+    }
+
+    pub fn get(&self, code: &str) -> Option<&'static str> {
+        self.trie.get(code).map(|(_, glyph)| *glyph)
+    }
+
+    /// Up to `n` shortcodes starting with `prefix` and their glyphs, shortest
+    /// first so an exact name is never buried under longer ones.
+    pub fn suggest(&self, prefix: &str, n: usize) -> Vec<(String, &'static str)> {
+        if !self.is_shortcode(prefix) {
+            return Vec::new();
+        }
+
+        let mut codes: Vec<(String, &'static str)> = self
+            .trie
+            .iter_prefix(prefix)
+            .map(|(code, _, glyph)| (code, *glyph))
+            .collect();
+
+        codes.sort_by(|a, b| (a.0.len(), &a.0).cmp(&(b.0.len(), &b.0)));
+        codes.truncate(n);
+
+        codes
+    }
+}
+
+const EMOJI: &[(&str, &str)] = &[
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("joy", "😂"),
+    ("rofl", "🤣"),
+    ("laughing", "😆"),
+    ("sweat_smile", "😅"),
+    ("wink", "😉"),
+    ("blush", "😊"),
+    ("innocent", "😇"),
+    ("slightly_smiling_face", "🙂"),
+    ("upside_down_face", "🙃"),
+    ("heart_eyes", "😍"),
+    ("kissing_heart", "😘"),
+    ("yum", "😋"),
+    ("stuck_out_tongue", "😛"),
+    ("thinking", "🤔"),
+    ("neutral_face", "😐"),
+    ("expressionless", "😑"),
+    ("unamused", "😒"),
+    ("roll_eyes", "🙄"),
+    ("grimacing", "😬"),
+    ("relieved", "😌"),
+    ("pensive", "😔"),
+    ("sleepy", "😪"),
+    ("sleeping", "😴"),
+    ("mask", "😷"),
+    ("nerd_face", "🤓"),
+    ("sunglasses", "😎"),
+    ("confused", "😕"),
+    ("worried", "😟"),
+    ("open_mouth", "😮"),
+    ("astonished", "😲"),
+    ("flushed", "😳"),
+    ("cry", "😢"),
+    ("sob", "😭"),
+    ("scream", "😱"),
+    ("angry", "😠"),
+    ("rage", "😡"),
+    ("skull", "💀"),
+    ("poop", "💩"),
+    ("clown_face", "🤡"),
+    ("ghost", "👻"),
+    ("robot", "🤖"),
+    ("see_no_evil", "🙈"),
+    ("wave", "👋"),
+    ("ok_hand", "👌"),
+    ("+1", "👍"),
+    ("thumbsup", "👍"),
+    ("-1", "👎"),
+    ("thumbsdown", "👎"),
+    ("clap", "👏"),
+    ("raised_hands", "🙌"),
+    ("pray", "🙏"),
+    ("muscle", "💪"),
+    ("point_up", "☝️"),
+    ("point_right", "👉"),
+    ("eyes", "👀"),
+    ("brain", "🧠"),
+    ("heart", "❤️"),
+    ("broken_heart", "💔"),
+    ("sparkling_heart", "💖"),
+    ("100", "💯"),
+    ("fire", "🔥"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("zap", "⚡"),
+    ("boom", "💥"),
+    ("tada", "🎉"),
+    ("confetti_ball", "🎊"),
+    ("gift", "🎁"),
+    ("trophy", "🏆"),
+    ("rocket", "🚀"),
+    ("bug", "🐛"),
+    ("wrench", "🔧"),
+    ("hammer", "🔨"),
+    ("lock", "🔒"),
+    ("unlock", "🔓"),
+    ("key", "🔑"),
+    ("bulb", "💡"),
+    ("memo", "📝"),
+    ("book", "📖"),
+    ("calendar", "📅"),
+    ("email", "📧"),
+    ("phone", "📱"),
+    ("computer", "💻"),
+    ("package", "📦"),
+    ("chart_with_upwards_trend", "📈"),
+    ("warning", "⚠️"),
+    ("no_entry", "⛔"),
+    ("x", "❌"),
+    ("white_check_mark", "✅"),
+    ("heavy_check_mark", "✔️"),
+    ("question", "❓"),
+    ("exclamation", "❗"),
+    ("hourglass", "⌛"),
+    ("coffee", "☕"),
+    ("beer", "🍺"),
+    ("pizza", "🍕"),
+    ("cake", "🍰"),
+    ("apple", "🍎"),
+    ("sunny", "☀️"),
+    ("cloud", "☁️"),
+    ("umbrella", "☔"),
+    ("snowflake", "❄️"),
+    ("rainbow", "🌈"),
+    ("earth_americas", "🌎"),
+    ("dog", "🐶"),
+    ("cat", "🐱"),
+    ("unicorn", "🦄"),
+    ("snake", "🐍"),
+    ("crab", "🦀"),
+    ("turtle", "🐢"),
+    ("seedling", "🌱"),
+    ("evergreen_tree", "🌲"),
+    ("deciduous_tree", "🌳"),
+    ("rose", "🌹"),
+];
+
+const LATEX: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ϵ"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "ϕ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("sqrt", "√"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "·"),
+    ("circ", "∘"),
+    ("leq", "≤"),
+    ("geq", "≥"),
+    ("neq", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("propto", "∝"),
+    ("ll", "≪"),
+    ("gg", "≫"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("supset", "⊃"),
+    ("supseteq", "⊇"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("emptyset", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("land", "∧"),
+    ("lor", "∨"),
+    ("oplus", "⊕"),
+    ("otimes", "⊗"),
+    ("perp", "⊥"),
+    ("angle", "∠"),
+    ("degree", "°"),
+    ("leftarrow", "←"),
+    ("rightarrow", "→"),
+    ("uparrow", "↑"),
+    ("downarrow", "↓"),
+    ("leftrightarrow", "↔"),
+    ("Leftarrow", "⇐"),
+    ("Rightarrow", "⇒"),
+    ("Leftrightarrow", "⇔"),
+    ("mapsto", "↦"),
+    ("to", "→"),
+    ("implies", "⟹"),
+    ("iff", "⟺"),
+    ("ldots", "…"),
+    ("cdots", "⋯"),
+    ("aleph", "ℵ"),
+    ("hbar", "ℏ"),
+    ("ell", "ℓ"),
+    ("Re", "ℜ"),
+    ("Im", "ℑ"),
+    ("mathbb{N}", "ℕ"),
+    ("mathbb{Z}", "ℤ"),
+    ("mathbb{Q}", "ℚ"),
+    ("mathbb{R}", "ℝ"),
+    ("mathbb{C}", "ℂ"),
+];
+
+const HTML: &[(&str, &str)] = &[
+    ("amp", "&"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("quot", "\""),
+    ("apos", "'"),
+    ("nbsp", "\u{a0}"),
+    ("copy", "©"),
+    ("reg", "®"),
+    ("trade", "™"),
+    ("deg", "°"),
+    ("plusmn", "±"),
+    ("times", "×"),
+    ("divide", "÷"),
+    ("micro", "µ"),
+    ("para", "¶"),
+    ("sect", "§"),
+    ("middot", "·"),
+    ("bull", "•"),
+    ("hellip", "…"),
+    ("ndash", "–"),
+    ("mdash", "—"),
+    ("lsquo", "‘"),
+    ("rsquo", "’"),
+    ("ldquo", "“"),
+    ("rdquo", "”"),
+    ("laquo", "«"),
+    ("raquo", "»"),
+    ("iexcl", "¡"),
+    ("iquest", "¿"),
+    ("cent", "¢"),
+    ("pound", "£"),
+    ("euro", "€"),
+    ("yen", "¥"),
+    ("frac12", "½"),
+    ("frac14", "¼"),
+    ("frac34", "¾"),
+    ("sup2", "²"),
+    ("sup3", "³"),
+    ("larr", "←"),
+    ("rarr", "→"),
+    ("uarr", "↑"),
+    ("darr", "↓"),
+    ("harr", "↔"),
+    ("hearts", "♥"),
+    ("spades", "♠"),
+    ("clubs", "♣"),
+    ("diams", "♦"),
+    ("check", "✓"),
+    ("dagger", "†"),
+    ("Dagger", "‡"),
+    ("permil", "‰"),
+    ("infin", "∞"),
+    ("ne", "≠"),
+    ("le", "≤"),
+    ("ge", "≥"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<(ShortcodeSet, char)> {
+        ShortcodeSet::ALL
+            .iter()
+            .map(|set| (*set, set.default_sigil()))
+            .collect()
+    }
+
+    #[test]
+    fn it_completes_shortcodes() {
+        let shortcodes = Shortcodes::new(&defaults());
+
+        assert_eq!(
+            shortcodes.suggest(":smi", 1),
+            [(":smile".to_string(), "😄")]
+        );
+        assert_eq!(
+            shortcodes.suggest("\\alp", 5),
+            [("\\alpha".to_string(), "α")]
+        );
+        assert_eq!(shortcodes.get("&amp"), Some("&"));
+        assert_eq!(shortcodes.get(":+1"), Some("👍"));
+        assert!(shortcodes.suggest(":", 5).is_empty());
+        assert!(shortcodes.suggest("smi", 5).is_empty());
+    }
+
+    #[test]
+    fn it_uses_configured_sigils() {
+        let shortcodes = Shortcodes::new(&[(ShortcodeSet::Latex, '@')]);
+
+        assert_eq!(shortcodes.get("@beta"), Some("β"));
+        assert_eq!(shortcodes.get("\\beta"), None);
+        assert_eq!(shortcodes.get(":smile"), None);
+        assert!(!shortcodes.is_shortcode(":smile"));
+    }
+}
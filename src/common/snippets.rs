@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+
+use crate::util::errors::{ArborError, ConfigError, StorageError};
+
+use super::trie::Trie;
+
+/// Text-expander triggers such as `brb` → "be right back", kept in their own
+/// trie so they never mix with the learned vocabulary.
+#[derive(Default)]
+pub struct Snippets {
+    trie: Trie<String>,
+    /// File every change is written back to, if any.
+    path: Option<PathBuf>,
+}
+
+impl Snippets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the snippets in `path`, if it exists, and remembers it so later
+    /// changes are kept.
+    pub async fn load(path: &Path) -> Result<Self, ArborError> {
+        let mut snippets = Self::new();
+
+        if path.exists() {
+            snippets.insert_all(path, read(path).await?)?;
+        }
+
+        snippets.path = Some(path.to_path_buf());
+
+        Ok(snippets)
+    }
+
+    /// Adds every snippet in `path`, replacing triggers that already exist.
+    /// Returns the number of snippets read.
+    pub async fn import(&mut self, path: &Path) -> Result<usize, ArborError> {
+        let count = self.insert_all(path, read(path).await?)?;
+
+        self.save().await?;
+
+        Ok(count)
+    }
+
+    fn insert_all(
+        &mut self,
+        path: &Path,
+        snippets: Vec<(usize, String, String)>,
+    ) -> Result<usize, StorageError> {
+        let count = snippets.len();
+
+        for (record, trigger, expansion) in snippets {
+            self.trie
+                .insert_with_value(&trigger, expansion)
+                .map_err(|source| StorageError::Corrupt {
+                    path: path.to_path_buf(),
+                    record,
+                    source,
+                })?;
+        }
+
+        Ok(count)
+    }
+
+    pub async fn define(&mut self, trigger: &str, expansion: String) -> Result<(), ArborError> {
+        self.trie.insert_with_value(trigger, expansion)?;
+        self.save().await
+    }
+
+    /// Returns whether `trigger` was defined.
+    pub async fn remove(&mut self, trigger: &str) -> Result<bool, ArborError> {
+        let removed = self.trie.remove(trigger)?;
+
+        if removed {
+            self.save().await?;
+        }
+
+        Ok(removed)
+    }
+
+    pub fn get(&self, trigger: &str) -> Option<&str> {
+        self.trie
+            .get(trigger)
+            .map(|(_, expansion)| expansion.as_str())
+    }
+
+    /// Triggers starting with `prefix` and their expansions, alphabetically.
+    pub fn suggest(&self, prefix: &str) -> Vec<(String, &str)> {
+        let mut snippets: Vec<(String, &str)> = self
+            .trie
+            .iter_prefix(prefix)
+            .map(|(trigger, _, expansion)| (trigger, expansion.as_str()))
+            .collect();
+
+        snippets.sort();
+
+        snippets
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.iter_prefix("").count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.iter_prefix("").next().is_none()
+    }
+
+    async fn save(&self) -> Result<(), ArborError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let contents: String = self
+            .suggest("")
+            .into_iter()
+            .map(|(trigger, expansion)| format!("{}\t{}\n", trigger, escape(expansion)))
+            .collect();
+
+        tokio::fs::write(path, contents)
+            .await
+            .map_err(|source| StorageError::Write {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        Ok(())
+    }
+}
+
+/// Reads `trigger<TAB>expansion` lines, where `\n`, `\t` and `\\` in the
+/// expansion stand for a newline, a tab and a backslash, along with the
+/// 1-based line number of each.
+async fn read(path: &Path) -> Result<Vec<(usize, String, String)>, ArborError> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|source| StorageError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    let mut snippets = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (trigger, expansion) = line
+            .split_once('\t')
+            .filter(|(_, expansion)| !expansion.is_empty())
+            .ok_or_else(|| ConfigError::MissingExpansion {
+                path: path.to_path_buf(),
+                line: index + 1,
+                trigger: line.trim().to_string(),
+            })?;
+
+        snippets.push((index + 1, trigger.trim().to_string(), unescape(expansion)));
+    }
+
+    Ok(snippets)
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_defines_and_persists_snippets() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join("arbor-snippets");
+        let _ = std::fs::remove_file(&path);
+
+        let mut snippets = Snippets::load(&path).await?;
+
+        snippets.define("brb", "be right back".to_string()).await?;
+        snippets
+            .define("sig", "Best,\nJane\t(team lead)".to_string())
+            .await?;
+        snippets.define("bro", "brother".to_string()).await?;
+
+        assert_eq!(
+            snippets.suggest("br"),
+            [
+                ("brb".to_string(), "be right back"),
+                ("bro".to_string(), "brother")
+            ]
+        );
+        assert!(snippets.remove("bro").await?);
+        assert!(!snippets.remove("bro").await?);
+
+        let snippets = Snippets::load(&path).await?;
+
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets.get("sig"), Some("Best,\nJane\t(team lead)"));
+        assert!(Snippets::new()
+            .define("b-r-b", "be right back".to_string())
+            .await
+            .is_err());
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_imports_snippets() -> Result<(), Box<dyn Error>> {
+        let file = std::env::temp_dir().join("arbor-snippets-import");
+        std::fs::write(&file, "omw\ton my way\naddr\t1 Main St\\nSpringfield\n")?;
+
+        let mut snippets = Snippets::new();
+
+        assert_eq!(snippets.import(&file).await?, 2);
+        assert_eq!(snippets.get("addr"), Some("1 Main St\nSpringfield"));
+
+        std::fs::write(&file, "omw\ton my way\nty\n")?;
+
+        let error = snippets.import(&file).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            ArborError::Config(ConfigError::MissingExpansion { line: 2, .. })
+        ));
+
+        std::fs::remove_file(file)?;
+
+        Ok(())
+    }
+}
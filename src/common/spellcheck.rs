@@ -0,0 +1,221 @@
+use std::fmt::Write;
+
+use super::dictionary;
+
+/// Corrections further than this many edits away are not proposed.
+pub const DEFAULT_MAX_DISTANCE: usize = 2;
+
+/// Matches the words within a number of single-character insertions,
+/// deletions or substitutions of a target word. The state after reading a
+/// candidate's prefix is one row of the edit distance table, so a walk over
+/// the trie can drop a branch once every cell in it is past the limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Levenshtein {
+    target: Vec<char>,
+    max_distance: usize,
+}
+
+/// Edit distances from the prefix read so far to each prefix of the target.
+pub type Row = Vec<usize>;
+
+impl Levenshtein {
+    pub fn new(target: &str, max_distance: usize) -> Self {
+        Self {
+            target: target.chars().collect(),
+            max_distance,
+        }
+    }
+
+    pub fn start(&self) -> Row {
+        (0..=self.target.len()).collect()
+    }
+
+    pub fn step(&self, row: &Row, c: char) -> Row {
+        let mut next = Vec::with_capacity(row.len());
+        next.push(row[0] + 1);
+
+        for (j, t) in self.target.iter().enumerate() {
+            let substitution = row[j] + usize::from(*t != c);
+            let cell = substitution.min(row[j + 1] + 1).min(next[j] + 1);
+
+            next.push(cell);
+        }
+
+        next
+    }
+
+    /// The distance to the target if it's within the limit.
+    pub fn distance(&self, row: &Row) -> Option<usize> {
+        row.last().copied().filter(|d| *d <= self.max_distance)
+    }
+
+    pub fn can_match(&self, row: &Row) -> bool {
+        row.iter().min().is_some_and(|d| *d <= self.max_distance)
+    }
+
+    /// The distance from `word` to the target if it's within the limit.
+    pub fn distance_to(&self, word: &str) -> Option<usize> {
+        let mut row = self.start();
+
+        for c in word.chars() {
+            row = self.step(&row, c);
+
+            if !self.can_match(&row) {
+                return None;
+            }
+        }
+
+        self.distance(&row)
+    }
+}
+
+/// Lets dictionaries look for close words in their transducer.
+impl fst::Automaton for &Levenshtein {
+    type State = Row;
+
+    fn start(&self) -> Row {
+        Levenshtein::start(self)
+    }
+
+    fn is_match(&self, row: &Row) -> bool {
+        self.distance(row).is_some()
+    }
+
+    fn can_match(&self, row: &Row) -> bool {
+        Levenshtein::can_match(self, row)
+    }
+
+    fn accept(&self, row: &Row, byte: u8) -> Row {
+        self.step(row, dictionary::byte_char(byte))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    pub word: String,
+    pub distance: usize,
+    pub frequency: u32,
+}
+
+/// A word that isn't in any layer, with the closest known words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Misspelling {
+    pub word: String,
+    /// Byte offset of the word within the checked text.
+    pub offset: usize,
+    pub corrections: Vec<Correction>,
+}
+
+impl Misspelling {
+    /// Byte offset just past the end of the word.
+    pub fn end(&self) -> usize {
+        self.offset + self.word.len()
+    }
+
+    /// 1-based line and column of the word within `text`.
+    pub fn position(&self, text: &str) -> (usize, usize) {
+        let before = &text[..self.offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+
+        (line, column)
+    }
+}
+
+/// Writes misspellings as a JSON array with byte offsets into the text.
+pub fn to_json(misspellings: &[Misspelling]) -> String {
+    let mut json = String::from("[");
+
+    for (i, misspelling) in misspellings.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+
+        let corrections = misspelling
+            .corrections
+            .iter()
+            .map(|correction| {
+                format!(
+                    "{{\"word\":{},\"distance\":{},\"frequency\":{}}}",
+                    quote(&correction.word),
+                    correction.distance,
+                    correction.frequency
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let _ = write!(
+            json,
+            "{{\"word\":{},\"start\":{},\"end\":{},\"corrections\":[{}]}}",
+            quote(&misspelling.word),
+            misspelling.offset,
+            misspelling.end(),
+            corrections
+        );
+    }
+
+    json.push(']');
+
+    json
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_measures_edit_distance() {
+        let levenshtein = Levenshtein::new("kitten", 3);
+
+        assert_eq!(levenshtein.distance_to("sitting"), Some(3));
+        assert_eq!(levenshtein.distance_to("kitten"), Some(0));
+        assert_eq!(levenshtein.distance_to("kit"), Some(3));
+        assert_eq!(levenshtein.distance_to("sit"), None);
+        assert_eq!(Levenshtein::new("teh", 2).distance_to("the"), Some(2));
+    }
+
+    #[test]
+    fn it_reports_misspellings_as_json() {
+        let text = "hello\nwrold \"x\"";
+        let misspelling = Misspelling {
+            word: "wrold".to_string(),
+            offset: 6,
+            corrections: Vec::from([Correction {
+                word: "world".to_string(),
+                distance: 2,
+                frequency: 3,
+            }]),
+        };
+
+        assert_eq!(misspelling.position(text), (2, 1));
+        assert_eq!(
+            to_json(&[misspelling]),
+            "[{\"word\":\"wrold\",\"start\":6,\"end\":11,\"corrections\":\
+             [{\"word\":\"world\",\"distance\":2,\"frequency\":3}]}]"
+        );
+        assert_eq!(quote("a\"b\\"), "\"a\\\"b\\\\\"");
+    }
+}
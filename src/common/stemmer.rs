@@ -0,0 +1,356 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// Reduces inflected words to a common stem, e.g. "connected" and
+/// "connecting" to "connect", so they can be counted as one.
+pub trait Stemmer: Send + Sync {
+    fn stem(&self, word: &str) -> String;
+}
+
+/// The stemmer for a language such as `en-US`, if there is one.
+pub fn for_language(language: &str) -> Option<Box<dyn Stemmer>> {
+    match language.split('-').next() {
+        Some("en") => Some(Box::new(Porter)),
+        _ => None,
+    }
+}
+
+/// Martin Porter's English stemmer. Stems aren't always words, e.g.
+/// "happiness" becomes "happi", but every inflection maps to the same one.
+pub struct Porter;
+
+const STEP_2: [(&str, &str); 21] = [
+    ("ational", "ate"),
+    ("tional", "tion"),
+    ("enci", "ence"),
+    ("anci", "ance"),
+    ("izer", "ize"),
+    ("bli", "ble"),
+    ("alli", "al"),
+    ("entli", "ent"),
+    ("eli", "e"),
+    ("ousli", "ous"),
+    ("ization", "ize"),
+    ("ation", "ate"),
+    ("ator", "ate"),
+    ("alism", "al"),
+    ("iveness", "ive"),
+    ("fulness", "ful"),
+    ("ousness", "ous"),
+    ("aliti", "al"),
+    ("iviti", "ive"),
+    ("biliti", "ble"),
+    ("logi", "log"),
+];
+
+const STEP_3: [(&str, &str); 7] = [
+    ("icate", "ic"),
+    ("ative", ""),
+    ("alize", "al"),
+    ("iciti", "ic"),
+    ("ical", "ic"),
+    ("ful", ""),
+    ("ness", ""),
+];
+
+const STEP_4: [&str; 19] = [
+    "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ion", "ou",
+    "ism", "ate", "iti", "ous", "ive", "ize",
+];
+
+impl Stemmer for Porter {
+    fn stem(&self, word: &str) -> String {
+        let word = word.to_lowercase();
+
+        // NOTE: short words and words with other letters are left as they are
+        if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+            return word;
+        }
+
+        let mut word = word.into_bytes();
+
+        step_1a(&mut word);
+        step_1b(&mut word);
+        step_1c(&mut word);
+        replace_first(&mut word, &STEP_2, 0);
+        replace_first(&mut word, &STEP_3, 0);
+        step_4(&mut word);
+        step_5(&mut word);
+
+        String::from_utf8(word).unwrap_or_default()
+    }
+}
+
+fn step_1a(word: &mut Vec<u8>) {
+    if word.ends_with(b"sses") || word.ends_with(b"ies") {
+        word.truncate(word.len() - 2);
+    } else if word.ends_with(b"s") && !word.ends_with(b"ss") {
+        word.pop();
+    }
+}
+
+fn step_1b(word: &mut Vec<u8>) {
+    if word.ends_with(b"eed") {
+        if measure(&word[..word.len() - 3]) > 0 {
+            word.pop();
+        }
+
+        return;
+    }
+
+    let Some(suffix) = [&b"ed"[..], b"ing"]
+        .into_iter()
+        .find(|suffix| word.ends_with(suffix) && has_vowel(&word[..word.len() - suffix.len()]))
+    else {
+        return;
+    };
+
+    word.truncate(word.len() - suffix.len());
+
+    if word.ends_with(b"at") || word.ends_with(b"bl") || word.ends_with(b"iz") {
+        word.push(b'e');
+    } else if ends_with_double_consonant(word)
+        && !word.ends_with(b"l")
+        && !word.ends_with(b"s")
+        && !word.ends_with(b"z")
+    {
+        word.pop();
+    } else if measure(word) == 1 && ends_with_cvc(word) {
+        word.push(b'e');
+    }
+}
+
+fn step_1c(word: &mut [u8]) {
+    let last = word.len() - 1;
+
+    if word[last] == b'y' && has_vowel(&word[..last]) {
+        word[last] = b'i';
+    }
+}
+
+/// Replaces the first suffix in `rules` the word ends with, if what comes
+/// before it measures more than `min_measure`.
+fn replace_first(word: &mut Vec<u8>, rules: &[(&str, &str)], min_measure: usize) {
+    let Some((suffix, replacement)) = rules
+        .iter()
+        .find(|(suffix, _)| word.ends_with(suffix.as_bytes()))
+    else {
+        return;
+    };
+
+    let stem = word.len() - suffix.len();
+
+    if measure(&word[..stem]) > min_measure {
+        word.truncate(stem);
+        word.extend_from_slice(replacement.as_bytes());
+    }
+}
+
+fn step_4(word: &mut Vec<u8>) {
+    let Some(suffix) = STEP_4
+        .iter()
+        .find(|suffix| word.ends_with(suffix.as_bytes()))
+    else {
+        return;
+    };
+
+    let stem = &word[..word.len() - suffix.len()];
+
+    // NOTE: "ion" only goes after s or t, e.g. "adoption" but not "onion"
+    if *suffix == "ion" && !stem.ends_with(b"s") && !stem.ends_with(b"t") {
+        return;
+    }
+
+    if measure(stem) > 1 {
+        word.truncate(stem.len());
+    }
+}
+
+fn step_5(word: &mut Vec<u8>) {
+    if word.ends_with(b"e") {
+        let stem = &word[..word.len() - 1];
+        let m = measure(stem);
+
+        if m > 1 || (m == 1 && !ends_with_cvc(stem)) {
+            word.pop();
+        }
+    }
+
+    if measure(word) > 1 && ends_with_double_consonant(word) && word.ends_with(b"l") {
+        word.pop();
+    }
+}
+
+/// Whether the letter at `i` is a consonant. A `y` is one only at the start
+/// or after a vowel, as in "yes" or "toy".
+fn is_consonant(word: &[u8], i: usize) -> bool {
+    match word[i] {
+        b'a' | b'e' | b'i' | b'o' | b'u' => false,
+        b'y' => i == 0 || !is_consonant(word, i - 1),
+        _ => true,
+    }
+}
+
+/// How many vowel-consonant sequences the word has, e.g. 0 for "tree", 1
+/// for "trouble" and 2 for "private".
+fn measure(word: &[u8]) -> usize {
+    let mut m = 0;
+    let mut i = 0;
+
+    while i < word.len() && is_consonant(word, i) {
+        i += 1;
+    }
+
+    loop {
+        while i < word.len() && !is_consonant(word, i) {
+            i += 1;
+        }
+
+        if i == word.len() {
+            return m;
+        }
+
+        while i < word.len() && is_consonant(word, i) {
+            i += 1;
+        }
+
+        m += 1;
+    }
+}
+
+fn has_vowel(word: &[u8]) -> bool {
+    (0..word.len()).any(|i| !is_consonant(word, i))
+}
+
+fn ends_with_double_consonant(word: &[u8]) -> bool {
+    let n = word.len();
+
+    n >= 2 && word[n - 1] == word[n - 2] && is_consonant(word, n - 1)
+}
+
+/// Whether the word ends consonant-vowel-consonant with the last one not
+/// `w`, `x` or `y`, as in "hop" or "fil" but not "snow".
+fn ends_with_cvc(word: &[u8]) -> bool {
+    let n = word.len();
+
+    n >= 3
+        && is_consonant(word, n - 3)
+        && !is_consonant(word, n - 2)
+        && is_consonant(word, n - 1)
+        && !matches!(word[n - 1], b'w' | b'x' | b'y')
+}
+
+/// Learned words grouped by their stem.
+pub struct StemIndex {
+    stemmer: Box<dyn Stemmer>,
+    stems: HashMap<String, BTreeSet<String>>,
+}
+
+impl StemIndex {
+    pub fn new(stemmer: Box<dyn Stemmer>) -> Self {
+        Self {
+            stemmer,
+            stems: HashMap::new(),
+        }
+    }
+
+    pub fn stem(&self, word: &str) -> String {
+        self.stemmer.stem(word)
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        self.stems
+            .entry(self.stemmer.stem(word))
+            .or_default()
+            .insert(word.to_string());
+    }
+
+    pub fn remove(&mut self, word: &str) {
+        let stem = self.stemmer.stem(word);
+
+        if let Some(words) = self.stems.get_mut(&stem) {
+            words.remove(word);
+
+            if words.is_empty() {
+                self.stems.remove(&stem);
+            }
+        }
+    }
+
+    /// Indexed words with the same stem as `word`, alphabetically.
+    pub fn family(&self, word: &str) -> Vec<&str> {
+        self.stems
+            .get(&self.stemmer.stem(word))
+            .map(|words| words.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Every stem and the words indexed under it.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &BTreeSet<String>)> {
+        self.stems
+            .iter()
+            .map(|(stem, words)| (stem.as_str(), words))
+    }
+
+    pub fn len(&self) -> usize {
+        self.stems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stems.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stems_english_words() {
+        let cases = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("cats", "cat"),
+            ("agreed", "agre"),
+            ("running", "run"),
+            ("runs", "run"),
+            ("hoping", "hope"),
+            ("hopping", "hop"),
+            ("happy", "happi"),
+            ("happiness", "happi"),
+            ("relational", "relat"),
+            ("conditional", "condit"),
+            ("generalization", "gener"),
+            ("adoption", "adopt"),
+            ("controlling", "control"),
+            ("Connected", "connect"),
+            ("is", "is"),
+        ];
+
+        for (word, stem) in cases {
+            assert_eq!(Porter.stem(word), stem, "stem of {}", word);
+        }
+
+        assert!(for_language("en-GB").is_some());
+        assert!(for_language("tr-TR").is_none());
+    }
+
+    #[test]
+    fn it_groups_words_by_stem() {
+        let mut index = StemIndex::new(Box::new(Porter));
+
+        for word in ["connect", "connected", "connecting", "runner", "running"] {
+            index.insert(word);
+        }
+
+        assert_eq!(
+            index.family("connections"),
+            ["connect", "connected", "connecting"]
+        );
+        assert_eq!(index.family("runs"), ["running"]);
+
+        index.remove("running");
+
+        assert!(index.family("run").is_empty());
+        assert_eq!(index.len(), 2);
+    }
+}
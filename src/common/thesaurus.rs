@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::util::errors::{ArborError, ConfigError, StorageError};
+
+/// How a word relates to another. Every relation goes both ways, so
+/// "glad" is a synonym of "happy" as much as "happy" is one of "glad".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Relation {
+    Synonym,
+    Antonym,
+    Related,
+}
+
+impl Relation {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "synonym" => Some(Relation::Synonym),
+            "antonym" => Some(Relation::Antonym),
+            "related" => Some(Relation::Related),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Relation::Synonym => "synonym",
+            Relation::Antonym => "antonym",
+            Relation::Related => "related",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelatedWord {
+    pub word: String,
+    pub relation: Relation,
+}
+
+/// Words mapped to the words related to them, loaded from a thesaurus file.
+#[derive(Default)]
+pub struct Thesaurus {
+    entries: HashMap<String, Vec<(Relation, String)>>,
+}
+
+impl Thesaurus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads `word<TAB>relation<TAB>related, related, ...` lines, where the
+    /// relation is `synonym`, `antonym` or `related`.
+    pub async fn load(path: &Path) -> Result<Self, ArborError> {
+        let contents =
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|source| StorageError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+
+        let mut thesaurus = Self::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut columns = line.split('\t').map(str::trim);
+            let word = columns.next().unwrap_or_default();
+            let relation = columns.next().unwrap_or_default();
+            let relation =
+                Relation::parse(relation).ok_or_else(|| ConfigError::InvalidRelation {
+                    path: path.to_path_buf(),
+                    line: index + 1,
+                    word: word.to_string(),
+                    relation: relation.to_string(),
+                })?;
+
+            for related in columns
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|related| !related.is_empty())
+            {
+                thesaurus.insert(word, relation, related);
+            }
+        }
+
+        Ok(thesaurus)
+    }
+
+    /// Records that `word` and `related` are related both ways.
+    pub fn insert(&mut self, word: &str, relation: Relation, related: &str) {
+        if word == related {
+            return;
+        }
+
+        for (from, to) in [(word, related), (related, word)] {
+            let entry = self.entries.entry(from.to_string()).or_default();
+
+            if !entry.iter().any(|(r, known)| *r == relation && known == to) {
+                entry.push((relation, to.to_string()));
+            }
+        }
+    }
+
+    /// Words related to `word`, in the order they were added.
+    pub fn lookup(&self, word: &str) -> &[(Relation, String)] {
+        self.entries.get(word).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.entries.contains_key(word)
+    }
+
+    /// Number of words with at least one related word.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_loads_thesaurus() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join("arbor-thesaurus");
+        std::fs::write(
+            &path,
+            "happy\tsynonym\tglad, joyful\nhappy\tantonym\tsad\nglad\tsynonym\thappy\n",
+        )?;
+
+        let thesaurus = Thesaurus::load(&path).await?;
+
+        assert_eq!(
+            thesaurus.lookup("happy"),
+            [
+                (Relation::Synonym, "glad".to_string()),
+                (Relation::Synonym, "joyful".to_string()),
+                (Relation::Antonym, "sad".to_string())
+            ]
+        );
+        assert_eq!(
+            thesaurus.lookup("sad"),
+            [(Relation::Antonym, "happy".to_string())]
+        );
+        assert_eq!(
+            thesaurus.lookup("glad"),
+            [(Relation::Synonym, "happy".to_string())],
+            "relations are not repeated"
+        );
+        assert!(thesaurus.lookup("tree").is_empty());
+        assert_eq!(thesaurus.len(), 4);
+
+        std::fs::write(&path, "happy\tsimilar\tglad\n")?;
+
+        assert!(matches!(
+            Thesaurus::load(&path).await,
+            Err(ArborError::Config(ConfigError::InvalidRelation {
+                line: 1,
+                ..
+            }))
+        ));
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::util::errors::{ConfigError, TokenizerError};
+
+use super::trie::Trie;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Apostrophes {
+    Keep,
+    Strip,
+    Split,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hyphens {
+    Keep,
+    Split,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenizerRules {
+    pub strip_punctuation: bool,
+    pub apostrophes: Apostrophes,
+    pub hyphens: Hyphens,
+    pub allow_numbers: bool,
+    pub detect_urls: bool,
+    pub detect_emails: bool,
+    /// Rejects whole chunks that look like passwords or API keys, so no
+    /// piece of them is learned.
+    pub detect_secrets: bool,
+    pub min_length: usize,
+    pub max_length: usize,
+}
+
+impl Default for TokenizerRules {
+    fn default() -> Self {
+        Self {
+            strip_punctuation: true,
+            apostrophes: Apostrophes::Strip,
+            hyphens: Hyphens::Split,
+            allow_numbers: false,
+            detect_urls: true,
+            detect_emails: true,
+            detect_secrets: true,
+            min_length: 2,
+            max_length: 32,
+        }
+    }
+}
+
+impl TokenizerRules {
+    pub fn for_language(language: &str) -> Self {
+        match language {
+            // NOTE: "l'homme" is two words rather than "lhomme"
+            "fr-FR" | "it-IT" => Self {
+                apostrophes: Apostrophes::Split,
+                ..Self::default()
+            },
+            // NOTE: compound nouns get long
+            "de-DE" | "nl-NL" | "fi-FI" => Self {
+                max_length: 64,
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// The rules for `language` with the overrides of a tokenizer config on
+    /// top. Each line is `rule = value`; lines after a `[language]` header,
+    /// e.g. `[fr]` or `[de-DE]`, only apply to that language.
+    pub fn with_overrides(language: &str, config: &str) -> Result<Self, ConfigError> {
+        let mut rules = Self::for_language(language);
+        let mut applies = true;
+
+        for (index, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let invalid = || ConfigError::TokenizerRule {
+                line: index + 1,
+                rule: line.to_string(),
+            };
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let section = section.trim();
+                applies = section == language || language.split('-').next() == Some(section);
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(invalid)?;
+
+            // NOTE: rules of other languages are checked too
+            let mut overridden = rules.clone();
+            overridden
+                .set(name.trim(), value.trim())
+                .ok_or_else(invalid)?;
+
+            if applies {
+                rules = overridden;
+            }
+        }
+
+        Ok(rules)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Option<()> {
+        let flag = || match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+
+        match name {
+            "strip-punctuation" => self.strip_punctuation = flag()?,
+            "apostrophes" => {
+                self.apostrophes = match value {
+                    "keep" => Apostrophes::Keep,
+                    "strip" => Apostrophes::Strip,
+                    "split" => Apostrophes::Split,
+                    _ => return None,
+                }
+            }
+            "hyphens" => {
+                self.hyphens = match value {
+                    "keep" => Hyphens::Keep,
+                    "split" => Hyphens::Split,
+                    _ => return None,
+                }
+            }
+            "allow-numbers" => self.allow_numbers = flag()?,
+            "detect-urls" => self.detect_urls = flag()?,
+            "detect-emails" => self.detect_emails = flag()?,
+            "detect-secrets" => self.detect_secrets = flag()?,
+            "min-length" => self.min_length = value.parse().ok()?,
+            "max-length" => self.max_length = value.parse().ok()?,
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    /// Byte offset of the token within the tokenized text.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    TooShort,
+    TooLong,
+    Number,
+    Url,
+    Email,
+    Secret,
+    InvalidCharacter(char),
+    StopWord,
+    Blocked,
+    /// Matched the named filter rule, e.g. `card number`.
+    Rule(String),
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::TooShort => write!(f, "too short"),
+            Rejection::TooLong => write!(f, "too long"),
+            Rejection::Number => write!(f, "number"),
+            Rejection::Url => write!(f, "URL"),
+            Rejection::Email => write!(f, "email address"),
+            Rejection::Secret => write!(f, "secret"),
+            Rejection::InvalidCharacter(c) => write!(f, "invalid character '{}'", c),
+            Rejection::StopWord => write!(f, "stop word"),
+            Rejection::Blocked => write!(f, "blocked"),
+            Rejection::Rule(name) => write!(f, "looks like {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejected {
+    pub token: Token,
+    pub reason: Rejection,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TokenReport {
+    pub accepted: Vec<Token>,
+    pub rejected: Vec<Rejected>,
+}
+
+impl TokenReport {
+    pub fn words(&self) -> Vec<String> {
+        self.accepted
+            .iter()
+            .map(|token| token.text.clone())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Tokenizer {
+    rules: TokenizerRules,
+}
+
+impl Tokenizer {
+    pub fn new(rules: TokenizerRules) -> Self {
+        Self { rules }
+    }
+
+    pub fn for_language(language: &str) -> Self {
+        Self::new(TokenizerRules::for_language(language))
+    }
+
+    pub fn get_rules(&self) -> &TokenizerRules {
+        &self.rules
+    }
+
+    pub fn tokenize(&self, text: &str) -> TokenReport {
+        let mut report = TokenReport::default();
+
+        for (offset, chunk) in split_with_offsets(text, char::is_whitespace) {
+            self.tokenize_chunk(chunk, offset, &mut report);
+        }
+
+        report
+    }
+
+    /// Checks that `word` would be accepted as a single token, as is.
+    pub fn check(&self, word: &str) -> Result<(), TokenizerError> {
+        let mut report = TokenReport::default();
+
+        self.classify(word.to_string(), 0, &mut report);
+
+        match report.rejected.pop() {
+            Some(Rejected { token, reason }) => Err(TokenizerError::Rejected {
+                token: token.text,
+                offset: token.offset,
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn tokenize_chunk(&self, chunk: &str, offset: usize, report: &mut TokenReport) {
+        let token = |text: &str, offset: usize| Token {
+            text: text.to_string(),
+            offset,
+        };
+
+        if self.rules.detect_urls && looks_like_url(chunk) {
+            report.rejected.push(Rejected {
+                token: token(chunk, offset),
+                reason: Rejection::Url,
+            });
+            return;
+        }
+
+        if self.rules.detect_emails && looks_like_email(chunk) {
+            report.rejected.push(Rejected {
+                token: token(chunk, offset),
+                reason: Rejection::Email,
+            });
+            return;
+        }
+
+        if self.rules.detect_secrets && looks_like_secret(chunk.trim_matches(is_punctuation)) {
+            report.rejected.push(Rejected {
+                token: token(chunk, offset),
+                reason: Rejection::Secret,
+            });
+            return;
+        }
+
+        let (chunk, offset) = if self.rules.strip_punctuation {
+            let trimmed = chunk.trim_start_matches(is_punctuation);
+
+            (
+                trimmed.trim_end_matches(is_punctuation),
+                offset + chunk.len() - trimmed.len(),
+            )
+        } else {
+            (chunk, offset)
+        };
+
+        let pieces: Vec<(usize, &str)> = match self.rules.hyphens {
+            Hyphens::Split => split_with_offsets(chunk, |c| c == '-').collect(),
+            Hyphens::Keep => vec![(0, chunk)],
+        };
+
+        for (piece_offset, piece) in pieces {
+            let offset = offset + piece_offset;
+
+            match self.rules.apostrophes {
+                Apostrophes::Keep => self.classify(piece.to_string(), offset, report),
+                Apostrophes::Strip => {
+                    self.classify(piece.replace(is_apostrophe, ""), offset, report)
+                }
+                Apostrophes::Split => {
+                    for (part_offset, part) in split_with_offsets(piece, is_apostrophe) {
+                        self.classify(part.to_string(), offset + part_offset, report);
+                    }
+                }
+            }
+        }
+    }
+
+    fn classify(&self, text: String, offset: usize, report: &mut TokenReport) {
+        if text.is_empty() {
+            return;
+        }
+
+        let length = text.chars().count();
+
+        let reason = if !self.rules.allow_numbers && text.chars().all(|c| c.is_ascii_digit()) {
+            Some(Rejection::Number)
+        } else if length < self.rules.min_length {
+            Some(Rejection::TooShort)
+        } else if length > self.rules.max_length {
+            Some(Rejection::TooLong)
+        } else {
+            text.chars()
+                .find(|c| !Trie::is_valid_char(*c))
+                .map(Rejection::InvalidCharacter)
+        };
+
+        let token = Token { text, offset };
+
+        match reason {
+            Some(reason) => report.rejected.push(Rejected { token, reason }),
+            None => report.accepted.push(token),
+        }
+    }
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || matches!(c, '“' | '”' | '‘' | '’' | '…' | '«' | '»')
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '’'
+}
+
+fn looks_like_url(chunk: &str) -> bool {
+    chunk.contains("://") || chunk.starts_with("www.")
+}
+
+fn looks_like_email(chunk: &str) -> bool {
+    match chunk.split_once('@') {
+        Some((user, domain)) => !user.is_empty() && domain.contains('.'),
+        None => false,
+    }
+}
+
+/// Prefixes of well-known API keys and tokens, e.g. GitHub's `ghp_`.
+const SECRET_PREFIXES: [&str; 12] = [
+    "sk-",
+    "sk_",
+    "pk_",
+    "rk_",
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "github_pat_",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+    "AIza",
+];
+
+/// Whether `chunk` looks like a password, API key or other secret: a known
+/// key prefix followed by enough characters, or a long run of letters and
+/// digits too random to be a word.
+pub fn looks_like_secret(chunk: &str) -> bool {
+    if SECRET_PREFIXES
+        .iter()
+        .any(|prefix| chunk.starts_with(prefix) && chunk.len() >= prefix.len() + 12)
+    {
+        return true;
+    }
+
+    // NOTE: camel-cased identifiers are random enough too, so ask for digits
+    chunk.chars().count() >= 16
+        && chunk.chars().any(|c| c.is_ascii_digit())
+        && chunk.chars().any(|c| c.is_alphabetic())
+        && !chunk.chars().any(char::is_whitespace)
+        && entropy(chunk) >= 3.5
+}
+
+/// Shannon entropy of the characters in `text`, in bits per character.
+pub fn entropy(text: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+
+    for c in text.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+
+    let length = text.chars().count() as f64;
+
+    counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / length;
+
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Splits `text` on `separator`, yielding non-empty pieces with their byte offsets.
+fn split_with_offsets<'a>(
+    text: &'a str,
+    separator: impl Fn(char) -> bool + 'a,
+) -> impl Iterator<Item = (usize, &'a str)> + 'a {
+    let mut start = 0;
+    let mut pieces = Vec::new();
+
+    for (i, c) in text.char_indices() {
+        if separator(c) {
+            if start < i {
+                pieces.push((start, &text[start..i]));
+            }
+            start = i + c.len_utf8();
+        }
+    }
+
+    if start < text.len() {
+        pieces.push((start, &text[start..]));
+    }
+
+    pieces.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_strips_punctuation() {
+        let report = Tokenizer::default().tokenize("Hello, world! (again)");
+
+        assert_eq!(report.words(), vec!["Hello", "world", "again"]);
+        assert_eq!(report.accepted[1].offset, 7);
+        assert_eq!(report.accepted[2].offset, 15);
+        assert!(report.rejected.is_empty());
+    }
+
+    #[test]
+    fn it_handles_apostrophes_and_hyphens() {
+        let report = Tokenizer::for_language("en-US").tokenize("don't well-known");
+
+        assert_eq!(report.words(), vec!["dont", "well", "known"]);
+        assert_eq!(report.accepted[2].offset, 11);
+
+        let report = Tokenizer::for_language("fr-FR").tokenize("l'homme");
+
+        assert_eq!(report.words(), vec!["homme"]);
+        assert_eq!(report.rejected[0].reason, Rejection::TooShort);
+    }
+
+    #[test]
+    fn it_overrides_rules_per_language() -> Result<(), ConfigError> {
+        let config = "allow-numbers = true\n\n[fr]\napostrophes = keep # élision\n[de-DE]\nmax-length = 80\n";
+
+        let french = TokenizerRules::with_overrides("fr-FR", config)?;
+
+        assert!(french.allow_numbers);
+        assert_eq!(french.apostrophes, Apostrophes::Keep);
+        assert_eq!(french.max_length, 32);
+
+        let german = TokenizerRules::with_overrides("de-DE", config)?;
+
+        assert_eq!(german.apostrophes, Apostrophes::Strip);
+        assert_eq!(german.max_length, 80);
+
+        assert!(matches!(
+            TokenizerRules::with_overrides("en-US", "[fr]\nhyphens = maybe"),
+            Err(ConfigError::TokenizerRule { line: 2, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_checks_single_words() {
+        let tokenizer = Tokenizer::default();
+
+        assert!(tokenizer.check("hello").is_ok());
+        assert_eq!(
+            tokenizer.check("hello,").unwrap_err(),
+            TokenizerError::Rejected {
+                token: "hello,".to_string(),
+                offset: 0,
+                reason: Rejection::InvalidCharacter(','),
+            }
+        );
+    }
+
+    #[test]
+    fn it_detects_secrets() {
+        let report = Tokenizer::default()
+            .tokenize("key sk-AbcDEFghijKLMnop and Xy7Qp2Lm9Vr4Tz8Kw1Bn, internationalization");
+
+        assert_eq!(report.words(), vec!["key", "and", "internationalization"]);
+        assert!(report
+            .rejected
+            .iter()
+            .all(|rejected| rejected.reason == Rejection::Secret));
+        assert_eq!(report.rejected.len(), 2);
+        assert!(!looks_like_secret("getElementsByTagName"));
+        assert_eq!(entropy("aaaa"), 0.0);
+        assert_eq!(entropy("abcd"), 2.0);
+    }
+
+    #[test]
+    fn it_reports_rejected_tokens() {
+        let report = Tokenizer::default()
+            .tokenize("mail me@example.com at https://example.com 42 times, cafés a");
+
+        assert_eq!(report.words(), vec!["mail", "at", "times"]);
+        assert_eq!(
+            report
+                .rejected
+                .iter()
+                .map(|rejected| rejected.reason.clone())
+                .collect::<Vec<Rejection>>(),
+            vec![
+                Rejection::Email,
+                Rejection::Url,
+                Rejection::Number,
+                Rejection::InvalidCharacter('é'),
+                Rejection::TooShort,
+            ]
+        );
+    }
+}
@@ -1,102 +1,621 @@
+use std::collections::hash_map::Iter;
 use std::collections::HashMap;
+use std::time::Duration;
 
 use crate::util::errors::TrieError;
 
-#[derive(Default, Debug)]
-pub struct TrieNode {
-    children: HashMap<char, Option<Box<TrieNode>>>,
+use super::pattern::{Pattern, PatternLimits, States};
+use super::spellcheck::{Levenshtein, Row};
+
+#[derive(Debug)]
+pub struct TrieNode<V = ()> {
+    children: HashMap<char, Option<Box<TrieNode<V>>>>,
     word_ends: bool,
+    frequency: u32,
+    /// Seconds since the Unix epoch, 0 when the word was never touched.
+    last_used: u64,
+    value: Option<V>,
+    /// Best descendants by rank key, kept only when the trie has a top-k cache.
+    top: Vec<(f64, String)>,
 }
 
-#[derive(Default)]
-pub struct Trie {
-    pub root: TrieNode,
+impl<V> Default for TrieNode<V> {
+    fn default() -> Self {
+        Self {
+            children: HashMap::new(),
+            word_ends: false,
+            frequency: 0,
+            last_used: 0,
+            value: None,
+            top: Vec::new(),
+        }
+    }
+}
+
+impl<V> Drop for TrieNode<V> {
+    // NOTE: the derived drop recurses once per level and overflows on long words
+    fn drop(&mut self) {
+        let mut stack: Vec<Box<TrieNode<V>>> = self
+            .children
+            .drain()
+            .filter_map(|(_, child)| child)
+            .collect();
+
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.children.drain().filter_map(|(_, child)| child));
+        }
+    }
+}
+
+/// A prefix tree whose words each carry a payload of type `V`.
+pub struct Trie<V = ()> {
+    pub root: TrieNode<V>,
+    top_k: Option<usize>,
+    /// Half-life in seconds the cache decays frequencies by, if any.
+    half_life: Option<f64>,
+    /// Characters accepted in keys, ASCII letters unless built otherwise.
+    is_valid: fn(char) -> bool,
+}
+
+impl<V> Default for Trie<V> {
+    fn default() -> Self {
+        Self {
+            root: TrieNode::default(),
+            top_k: None,
+            half_life: None,
+            is_valid: Trie::is_valid_char,
+        }
+    }
 }
 
 impl Trie {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Characters the trie accepts as keys.
+    pub fn is_valid_char(c: char) -> bool {
+        c.is_ascii_alphabetic()
+    }
+}
+
+impl<V> Trie<V> {
+    /// Creates a trie whose nodes cache their `k` most frequent descendants,
+    /// making `top` O(prefix length) for up to `k` results.
+    pub fn with_top_k(k: usize) -> Self {
         Self {
-            root: TrieNode::default(),
+            top_k: Some(k),
+            ..Self::default()
         }
     }
 
-    pub fn insert(word: String, node: &mut TrieNode, position: usize) -> Result<(), TrieError> {
-        if position == word.len() {
-            node.word_ends = true;
-            return Ok(());
+    /// Creates a trie whose keys may hold any character `is_valid` accepts,
+    /// e.g. the punctuation in shortcodes like `:+1` or `\alpha`.
+    pub fn with_valid_chars(is_valid: fn(char) -> bool) -> Self {
+        Self {
+            is_valid,
+            ..Self::default()
         }
+    }
 
-        let c = word.as_bytes()[position] as char;
+    /// Orders the top-k cache by frequency halved every `half_life` since a
+    /// word was last used, rather than by frequency alone. Words never used
+    /// rank first. Rebuilds every cache when the order changes.
+    pub fn set_half_life(&mut self, half_life: Option<Duration>) {
+        let half_life = half_life
+            .map(|half_life| half_life.as_secs_f64())
+            .filter(|half_life| *half_life > 0.0);
 
-        if !c.is_ascii_alphabetic() {
-            return Err(TrieError::InvalidCharacter);
+        if self.half_life == half_life {
+            return;
         }
 
-        let child = node
-            .children
-            .entry(c)
-            .or_insert_with(|| Some(Box::new(TrieNode::default())));
+        self.half_life = half_life;
 
-        if let Some(child) = child {
-            Self::insert(word, child.as_mut(), position + 1)?;
+        // NOTE: each node is rebuilt last with its word that is refreshed last,
+        // after all of its children, so any order of words rebuilds every cache
+        let words: Vec<String> = self.iter_prefix("").map(|(word, _, _)| word).collect();
+        for word in &words {
+            self.refresh_path(word);
         }
+    }
+
+    /// Inserts `word`, keeping its payload if it already has one and storing
+    /// `V::default()` otherwise.
+    pub fn insert(&mut self, word: &str) -> Result<(), TrieError>
+    where
+        V: Default,
+    {
+        let node = self.insert_node(word)?;
+
+        node.value.get_or_insert_with(V::default);
+        self.refresh_path(word);
+
+        Ok(())
+    }
+
+    /// Inserts `word`, replacing its payload with `value`.
+    pub fn insert_with_value(&mut self, word: &str, value: V) -> Result<(), TrieError> {
+        let node = self.insert_node(word)?;
+
+        node.value = Some(value);
+        self.refresh_path(word);
 
         Ok(())
     }
 
-    pub fn search(word: String, node: &TrieNode, position: usize) -> Result<bool, TrieError> {
-        if position == word.len() {
-            return Ok(node.word_ends);
+    /// Removes `word`, pruning the branch it leaves behind. Returns whether it existed.
+    pub fn remove(&mut self, word: &str) -> Result<bool, TrieError> {
+        self.validate(word)?;
+
+        let chars: Vec<char> = word.chars().collect();
+        let path = self.path(&chars);
+
+        if path.len() <= chars.len() || !path[chars.len()].word_ends {
+            return Ok(false);
         }
 
-        let c = word.as_bytes()[position] as char;
+        // NOTE: the deepest ancestor that still has to exist once the word is gone
+        let mut keep = chars.len();
+        if path[keep].children.is_empty() {
+            while keep > 0 {
+                keep -= 1;
 
-        if !c.is_ascii_alphabetic() {
-            return Err(TrieError::InvalidCharacter);
+                if keep == 0 || path[keep].word_ends || path[keep].children.len() > 1 {
+                    break;
+                }
+            }
         }
 
-        match node.children.get(&c) {
-            Some(child) => Self::search(word, child.as_deref().unwrap(), position + 1),
-            None => Ok(false),
+        let mut node = &mut self.root;
+        for c in &chars[..keep] {
+            node = match node
+                .children
+                .get_mut(c)
+                .and_then(|child| child.as_deref_mut())
+            {
+                Some(child) => child,
+                None => return Ok(false),
+            };
         }
+
+        if keep == chars.len() {
+            node.word_ends = false;
+            node.frequency = 0;
+            node.last_used = 0;
+            node.value = None;
+        } else {
+            node.children.remove(&chars[keep]);
+        }
+
+        self.refresh_path(word);
+
+        Ok(true)
     }
 
-    pub fn suggest(&self, prefix: &str) -> Result<Vec<String>, TrieError> {
+    /// Overrides the frequency of an existing word. Returns whether it existed.
+    pub fn set_frequency(&mut self, word: &str, frequency: u32) -> Result<bool, TrieError> {
+        self.validate(word)?;
+
+        let mut node = &mut self.root;
+        for c in word.chars() {
+            node = match node
+                .children
+                .get_mut(&c)
+                .and_then(|child| child.as_deref_mut())
+            {
+                Some(child) => child,
+                None => return Ok(false),
+            };
+        }
+
+        if !node.word_ends {
+            return Ok(false);
+        }
+
+        node.frequency = frequency;
+        self.refresh_path(word);
+
+        Ok(true)
+    }
+
+    /// Records that `word` was used at `timestamp`, keeping the latest time
+    /// seen. Returns whether the word exists.
+    pub fn touch(&mut self, word: &str, timestamp: u64) -> Result<bool, TrieError> {
+        self.validate(word)?;
+
+        let mut node = &mut self.root;
+        for c in word.chars() {
+            node = match node
+                .children
+                .get_mut(&c)
+                .and_then(|child| child.as_deref_mut())
+            {
+                Some(child) => child,
+                None => return Ok(false),
+            };
+        }
+
+        if !node.word_ends {
+            return Ok(false);
+        }
+
+        node.last_used = node.last_used.max(timestamp);
+
+        if self.half_life.is_some() {
+            self.refresh_path(word);
+        }
+
+        Ok(true)
+    }
+
+    /// The frequency and payload of `word`, if it exists.
+    pub fn get(&self, word: &str) -> Option<(u32, &V)> {
+        let node = self.find(word).filter(|node| node.word_ends)?;
+
+        Some((node.frequency, node.value.as_ref()?))
+    }
+
+    /// When `word` was last used, if it exists and was ever touched.
+    pub fn last_used(&self, word: &str) -> Option<u64> {
+        self.find(word)
+            .filter(|node| node.word_ends && node.last_used > 0)
+            .map(|node| node.last_used)
+    }
+
+    /// Returns up to `n` words starting with `prefix`, most frequent first, or
+    /// by decayed frequency once a half-life is set. Served from the node
+    /// cache when it holds at least `n` entries, and by scanning the whole
+    /// subtree otherwise.
+    pub fn top(&self, prefix: &str, n: usize) -> Vec<(String, u32, &V)> {
+        match self.top_k {
+            Some(k) if n <= k => {
+                let Some(node) = self.find(prefix) else {
+                    return Vec::new();
+                };
+
+                node.top
+                    .iter()
+                    .take(n)
+                    .filter_map(|(_, word)| {
+                        let node = self.find(word)?;
+
+                        Some((word.clone(), node.frequency, node.value.as_ref()?))
+                    })
+                    .collect()
+            }
+            _ => {
+                let mut words: Vec<(f64, (String, u32, &V))> = self
+                    .iter_prefix(prefix)
+                    .filter_map(|word| Some((self.rank_key(self.find(&word.0)?), word)))
+                    .collect();
+
+                words.sort_by(|(a, (a_word, ..)), (b, (b_word, ..))| {
+                    b.total_cmp(a).then_with(|| a_word.cmp(b_word))
+                });
+                words.truncate(n);
+
+                words.into_iter().map(|(_, word)| word).collect()
+            }
+        }
+    }
+
+    /// What the top-k cache orders words by, highest first.
+    fn rank_key(&self, node: &TrieNode<V>) -> f64 {
+        match self.half_life {
+            // NOTE: log2(frequency) + last_used / half_life orders words like
+            // their decayed frequency at any one time, and never goes stale
+            Some(half_life) if node.last_used > 0 => {
+                (node.frequency as f64).log2() + node.last_used as f64 / half_life
+            }
+            Some(_) => f64::INFINITY,
+            None => node.frequency as f64,
+        }
+    }
+
+    /// Nodes along `chars`, starting at the root and stopping where the path ends.
+    fn path(&self, chars: &[char]) -> Vec<&TrieNode<V>> {
+        let mut path = Vec::with_capacity(chars.len() + 1);
         let mut node = &self.root;
-        let mut current_letters = String::new();
 
-        for i in 0..prefix.len() {
-            let letter = prefix.as_bytes()[i] as char;
+        path.push(node);
 
-            if let Some(child) = node.children.get(&letter) {
-                node = child.as_deref().unwrap();
-                current_letters.push(letter);
-            } else {
-                return Ok(Vec::new());
+        for c in chars {
+            match node.children.get(c).and_then(|child| child.as_deref()) {
+                Some(child) => {
+                    node = child;
+                    path.push(node);
+                }
+                None => break,
             }
         }
 
-        let mut suggestion_list: Vec<String> = Vec::new();
+        path
+    }
+
+    /// Rebuilds the top-k caches along `word`'s path, bottom-up, from each
+    /// node's own word and its children's caches.
+    fn refresh_path(&mut self, word: &str) {
+        let Some(k) = self.top_k else {
+            return;
+        };
+
+        let chars: Vec<char> = word.chars().collect();
+        let offsets: Vec<usize> = word
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([word.len()])
+            .collect();
+        let path = self.path(&chars);
+
+        let mut caches: Vec<Vec<(f64, String)>> = vec![Vec::new(); path.len()];
+
+        for depth in (0..path.len()).rev() {
+            let node = path[depth];
+            let mut candidates: Vec<(f64, String)> = Vec::new();
+
+            if node.word_ends {
+                candidates.push((self.rank_key(node), word[..offsets[depth]].to_string()));
+            }
+
+            for (c, child) in &node.children {
+                if depth + 1 < path.len() && *c == chars[depth] {
+                    candidates.extend(caches[depth + 1].iter().cloned());
+                } else if let Some(child) = child {
+                    candidates.extend(child.top.iter().cloned());
+                }
+            }
+
+            candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+            candidates.truncate(k);
+
+            caches[depth] = candidates;
+        }
+
+        let mut caches = caches.into_iter();
+        let mut node = &mut self.root;
+
+        node.top = caches.next().unwrap_or_default();
+
+        for (c, cache) in chars.iter().zip(caches) {
+            node = match node
+                .children
+                .get_mut(c)
+                .and_then(|child| child.as_deref_mut())
+            {
+                Some(child) => child,
+                None => break,
+            };
+
+            node.top = cache;
+        }
+    }
+
+    fn insert_node(&mut self, word: &str) -> Result<&mut TrieNode<V>, TrieError> {
+        // NOTE: validate up front so a rejected word leaves no dangling nodes
+        self.validate(word)?;
+
+        let mut node = &mut self.root;
+
+        for c in word.chars() {
+            node = node
+                .children
+                .entry(c)
+                .or_insert_with(|| Some(Box::default()))
+                .get_or_insert_with(Box::default);
+        }
+
+        node.word_ends = true;
+        node.frequency += 1;
+
+        Ok(node)
+    }
+
+    pub fn search(&self, word: &str) -> Result<bool, TrieError> {
+        self.validate(word)?;
+
+        Ok(self.find(word).is_some_and(|node| node.word_ends))
+    }
+
+    pub fn suggest(&self, prefix: &str) -> Result<Vec<String>, TrieError> {
+        Ok(self.iter_prefix(prefix).map(|(word, _, _)| word).collect())
+    }
+
+    /// Same as `suggest`, pairing each word with the number of times it was inserted.
+    pub fn suggest_with_frequency(&self, prefix: &str) -> Result<Vec<(String, u32)>, TrieError> {
+        Ok(self
+            .iter_prefix(prefix)
+            .map(|(word, frequency, _)| (word, frequency))
+            .collect())
+    }
+
+    /// Same as `suggest`, pairing each word with its payload.
+    pub fn suggest_with_values(&self, prefix: &str) -> Result<Vec<(String, &V)>, TrieError> {
+        Ok(self
+            .iter_prefix(prefix)
+            .map(|(word, _, value)| (word, value))
+            .collect())
+    }
+
+    /// Same as `suggest`, returning each word's frequency and payload.
+    pub fn suggest_with_details(&self, prefix: &str) -> Result<Vec<(String, u32, &V)>, TrieError> {
+        Ok(self.iter_prefix(prefix).collect())
+    }
+
+    /// Words matching `pattern` in alphabetical order. Branches are dropped as
+    /// soon as the pattern can no longer match them, and the walk stops early
+    /// once either limit is reached, in which case `truncated` is set.
+    pub fn find_matches(&self, pattern: &Pattern, limits: PatternLimits) -> Matches<'_, V> {
+        let mut matches = Matches {
+            words: Vec::new(),
+            truncated: false,
+        };
+        let mut stack: Vec<(&TrieNode<V>, String, States)> =
+            vec![(&self.root, String::new(), pattern.start())];
+        let mut visited = 0;
+
+        while let Some((node, word, states)) = stack.pop() {
+            visited += 1;
+
+            if visited > limits.max_visited || matches.words.len() >= limits.max_results {
+                matches.truncated = true;
+                break;
+            }
+
+            if let (true, Some(value)) = (node.word_ends, &node.value) {
+                if pattern.is_match(&states) {
+                    matches.words.push((word.clone(), node.frequency, value));
+                }
+            }
+
+            let mut children: Vec<(&char, &TrieNode<V>)> = node
+                .children
+                .iter()
+                .filter_map(|(c, child)| Some((c, child.as_deref()?)))
+                .collect();
+
+            // NOTE: pushed in reverse so the smallest character is popped first
+            children.sort_unstable_by(|a, b| b.0.cmp(a.0));
+
+            for (c, child) in children {
+                let next = pattern.step(&states, *c);
+
+                if !next.is_empty() {
+                    let mut word = word.clone();
+                    word.push(*c);
+
+                    stack.push((child, word, next));
+                }
+            }
+        }
+
+        matches
+    }
+
+    /// Words within the edit distance allowed by `levenshtein`, with that
+    /// distance and their frequency. Branches are dropped as soon as every
+    /// word under them is too far away.
+    pub fn find_similar(&self, levenshtein: &Levenshtein) -> Vec<(String, usize, u32)> {
+        let mut similar = Vec::new();
+        let mut stack: Vec<(&TrieNode<V>, String, Row)> =
+            vec![(&self.root, String::new(), levenshtein.start())];
+
+        while let Some((node, word, row)) = stack.pop() {
+            if node.word_ends {
+                if let Some(distance) = levenshtein.distance(&row) {
+                    similar.push((word.clone(), distance, node.frequency));
+                }
+            }
+
+            for (c, child) in &node.children {
+                let Some(child) = child.as_deref() else {
+                    continue;
+                };
+
+                let next = levenshtein.step(&row, *c);
+
+                if levenshtein.can_match(&next) {
+                    let mut word = word.clone();
+                    word.push(*c);
+
+                    stack.push((child, word, next));
+                }
+            }
+        }
+
+        similar
+    }
+
+    /// Lazily walks the words starting with `prefix`, so taking the first few
+    /// only visits as much of the subtree as needed.
+    pub fn iter_prefix(&self, prefix: &str) -> Suggestions<'_, V> {
+        let mut suggestions = Suggestions {
+            stack: Vec::new(),
+            current: prefix.to_string(),
+            pending: None,
+        };
+
+        suggestions.pending = self.find(prefix);
+
+        suggestions
+    }
+
+    fn find(&self, word: &str) -> Option<&TrieNode<V>> {
+        let mut node = &self.root;
 
-        Self::consume_words(node, &mut suggestion_list, &mut current_letters);
+        for c in word.chars() {
+            node = node.children.get(&c)?.as_deref()?;
+        }
 
-        Ok(suggestion_list)
+        Some(node)
     }
 
-    fn consume_words(node: &TrieNode, word_list: &mut Vec<String>, current_letters: &mut String) {
-        if node.word_ends {
-            word_list.push(current_letters.clone());
+    fn validate(&self, word: &str) -> Result<(), TrieError> {
+        match word.char_indices().find(|(_, c)| !(self.is_valid)(*c)) {
+            Some((position, character)) => Err(TrieError::InvalidCharacter {
+                word: word.to_string(),
+                character,
+                position,
+            }),
+            None => Ok(()),
         }
+    }
+}
+
+/// Words found by `Trie::find_matches`, with their frequencies and payloads.
+#[derive(Debug, PartialEq)]
+pub struct Matches<'a, V> {
+    pub words: Vec<(String, u32, &'a V)>,
+    /// Whether a limit cut the search short, so more words may match.
+    pub truncated: bool,
+}
+
+/// Depth-first iterator over the words below a trie node.
+pub struct Suggestions<'a, V> {
+    stack: Vec<Iter<'a, char, Option<Box<TrieNode<V>>>>>,
+    current: String,
+    pending: Option<&'a TrieNode<V>>,
+}
+
+impl<'a, V> Iterator for Suggestions<'a, V> {
+    type Item = (String, u32, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.pending.take() {
+                self.stack.push(node.children.iter());
 
-        for (child_char, child_node) in &node.children {
-            current_letters.push(*child_char);
-            Self::consume_words(child_node.as_deref().unwrap(), word_list, current_letters);
-            current_letters.pop();
+                if let (true, Some(value)) = (node.word_ends, &node.value) {
+                    return Some((self.current.clone(), node.frequency, value));
+                }
+
+                continue;
+            }
+
+            match self.stack.last_mut()?.next() {
+                Some((c, child)) => {
+                    self.current.push(*c);
+                    self.pending = child.as_deref();
+
+                    if self.pending.is_none() {
+                        self.current.pop();
+                    }
+                }
+                None => {
+                    self.stack.pop();
+
+                    // NOTE: the bottom iterator belongs to the prefix node itself
+                    if !self.stack.is_empty() {
+                        self.current.pop();
+                    }
+                }
+            }
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::useless_vec)]
 mod tests {
     use std::error::Error;
 
@@ -104,7 +623,7 @@ mod tests {
 
     #[test]
     fn it_initializes_trie_node() {
-        let trie_node = TrieNode::default();
+        let trie_node: TrieNode = TrieNode::default();
 
         assert_eq!(trie_node.children.len(), 0);
         assert_eq!(trie_node.word_ends, false);
@@ -123,9 +642,9 @@ mod tests {
         let mut trie = Trie::new();
         let word = "test".to_string();
 
-        Trie::insert(word.clone(), &mut trie.root, 0)?;
+        trie.insert(&word)?;
 
-        let has_word = Trie::search(word.clone(), &trie.root, 0)?;
+        let has_word = trie.search(&word)?;
 
         assert!(has_word);
 
@@ -143,7 +662,7 @@ mod tests {
         ];
 
         for word in words {
-            Trie::insert(word.to_string(), &mut trie.root, 0)?;
+            trie.insert(word)?;
         }
 
         let result = trie.suggest("hel")?;
@@ -157,14 +676,278 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn it_counts_word_frequency() -> Result<(), Box<dyn Error>> {
+        let mut trie = Trie::new();
+
+        for word in ["hello", "hello", "help"] {
+            trie.insert(word)?;
+        }
+
+        let mut result = trie.suggest_with_frequency("hel")?;
+        result.sort();
+
+        assert_eq!(
+            result,
+            vec![("hello".to_string(), 2), ("help".to_string(), 1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_handles_very_long_words() -> Result<(), Box<dyn Error>> {
+        let mut trie = Trie::new();
+        let word = "a".repeat(100_000);
+
+        trie.insert(&word)?;
+
+        assert!(trie.search(&word)?);
+        assert!(!trie.search(&word[1..])?);
+        assert_eq!(trie.suggest("aaa")?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_iterates_suggestions_lazily() -> Result<(), Box<dyn Error>> {
+        let mut trie = Trie::new();
+
+        for word in ["car", "cart", "carton", "cat", "dog"] {
+            trie.insert(word)?;
+        }
+
+        let mut result: Vec<String> = trie.iter_prefix("ca").map(|(word, _, _)| word).collect();
+        result.sort();
+
+        assert_eq!(result, vec!["car", "cart", "carton", "cat"]);
+        assert_eq!(trie.iter_prefix("ca").take(2).count(), 2);
+        assert_eq!(trie.iter_prefix("x").count(), 0);
+        assert_eq!(trie.iter_prefix("").count(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_ranks_words_with_top_k_cache() -> Result<(), Box<dyn Error>> {
+        let mut cached = Trie::with_top_k(2);
+        let mut scanned = Trie::new();
+
+        for word in ["car", "cart", "cart", "cat", "cat", "cat", "dog"] {
+            cached.insert(word)?;
+            scanned.insert(word)?;
+        }
+
+        let expected = vec![("cat".to_string(), 3, &()), ("cart".to_string(), 2, &())];
+
+        assert_eq!(cached.top("ca", 2), expected);
+        assert_eq!(scanned.top("ca", 2), expected);
+        assert_eq!(cached.top("ca", 3), scanned.top("ca", 3));
+
+        cached.set_frequency("car", 5)?;
+
+        assert_eq!(cached.top("c", 1), vec![("car".to_string(), 5, &())]);
+
+        assert!(cached.remove("car")?);
+        assert!(!cached.remove("car")?);
+
+        assert_eq!(cached.top("ca", 2), expected);
+        assert!(cached.search("cart")?);
+
+        assert!(cached.remove("dog")?);
+
+        assert!(cached.top("d", 2).is_empty());
+        assert!(!cached.root.children.contains_key(&'d'));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_ranks_words_by_decayed_frequency() -> Result<(), Box<dyn Error>> {
+        let day = 24 * 60 * 60;
+        let mut cached = Trie::with_top_k(2);
+        let mut scanned = Trie::new();
+
+        for trie in [&mut cached, &mut scanned] {
+            for (word, frequency, last_used) in [("cart", 8, 10), ("cat", 3, 30), ("car", 3, 20)] {
+                trie.insert(word)?;
+                trie.set_frequency(word, frequency)?;
+                trie.touch(word, last_used * day)?;
+            }
+
+            trie.set_half_life(Some(Duration::from_secs(10 * day)));
+        }
+
+        // NOTE: by day 30 cart's 8 has halved twice and car's 3 once
+        let expected = vec![("cat".to_string(), 3, &()), ("cart".to_string(), 8, &())];
+
+        assert_eq!(cached.top("ca", 2), expected);
+        assert_eq!(scanned.top("ca", 2), expected);
+
+        cached.touch("car", 40 * day)?;
+
+        assert_eq!(cached.top("c", 1), vec![("car".to_string(), 3, &())]);
+
+        cached.set_half_life(None);
+
+        assert_eq!(cached.top("c", 1), vec![("cart".to_string(), 8, &())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_tracks_last_used_time() -> Result<(), Box<dyn Error>> {
+        let mut trie = Trie::new();
+
+        trie.insert("hello")?;
+
+        assert_eq!(trie.last_used("hello"), None);
+        assert!(trie.touch("hello", 200)?);
+        assert!(trie.touch("hello", 100)?);
+        assert!(!trie.touch("help", 300)?);
+        assert_eq!(trie.last_used("hello"), Some(200));
+        assert_eq!(trie.get("hello"), Some((1, &())));
+        assert_eq!(trie.get("hell"), None);
+        assert_eq!(trie.last_used("hell"), None);
+
+        trie.remove("hello")?;
+        trie.insert("hello")?;
+
+        assert_eq!(trie.last_used("hello"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_finds_pattern_matches() -> Result<(), Box<dyn Error>> {
+        let mut trie = Trie::new();
+
+        for word in ["hello", "hall", "hull", "help", "cat", "cot", "cut", "coat"] {
+            trie.insert(word)?;
+        }
+
+        let words = |matches: Matches<'_, ()>| {
+            matches
+                .words
+                .into_iter()
+                .map(|(word, _, _)| word)
+                .collect::<Vec<String>>()
+        };
+
+        let matches = trie.find_matches(&Pattern::parse("h?ll*")?, PatternLimits::default());
+
+        assert!(!matches.truncated);
+        assert_eq!(words(matches), ["hall", "hello", "hull"]);
+        assert_eq!(
+            words(trie.find_matches(&Pattern::parse("c[ao]t")?, PatternLimits::default())),
+            ["cat", "cot"]
+        );
+
+        let limits = PatternLimits {
+            max_results: 2,
+            max_visited: 1000,
+        };
+        let matches = trie.find_matches(&Pattern::parse("*")?, limits);
+
+        assert!(matches.truncated);
+        assert_eq!(words(matches), ["cat", "coat"]);
+
+        let limits = PatternLimits {
+            max_results: 100,
+            max_visited: 3,
+        };
+
+        assert!(trie.find_matches(&Pattern::parse("*t")?, limits).truncated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_finds_similar_words() -> Result<(), Box<dyn Error>> {
+        let mut trie = Trie::new();
+
+        for word in ["cat", "cart", "coat", "dog", "cat"] {
+            trie.insert(word)?;
+        }
+
+        let mut similar = trie.find_similar(&Levenshtein::new("cst", 1));
+        similar.sort();
+
+        assert_eq!(similar, [("cat".to_string(), 1, 2)]);
+
+        let mut similar = trie.find_similar(&Levenshtein::new("cst", 2));
+        similar.sort();
+
+        assert_eq!(
+            similar,
+            [
+                ("cart".to_string(), 2, 1),
+                ("cat".to_string(), 1, 2),
+                ("coat".to_string(), 2, 1)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_accepts_custom_key_characters() -> Result<(), Box<dyn Error>> {
+        let mut trie: Trie<&str> = Trie::with_valid_chars(|c| !c.is_whitespace());
+
+        trie.insert_with_value(":+1", "👍")?;
+        trie.insert_with_value("\\alpha", "α")?;
+
+        assert!(trie.search(":+1")?);
+        assert_eq!(trie.get("\\alpha").map(|(_, glyph)| *glyph), Some("α"));
+        assert!(trie.insert_with_value(":thumbs up", "👍").is_err());
+        assert!(Trie::new().insert(":+1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_stores_word_payloads() -> Result<(), Box<dyn Error>> {
+        let mut trie: Trie<String> = Trie::default();
+
+        trie.insert_with_value("sku", "Stock unit".to_string())?;
+        trie.insert("sku")?;
+        trie.insert("skip")?;
+
+        let mut result = trie.suggest_with_values("sk")?;
+        result.sort();
+
+        assert_eq!(
+            result,
+            vec![
+                ("skip".to_string(), &String::new()),
+                ("sku".to_string(), &"Stock unit".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn it_inserts_word_with_non_ascii_character() -> Result<(), Box<dyn Error>> {
         let mut trie = Trie::new();
         let word = "~~~".to_string();
 
         assert_eq!(
-            Trie::insert(word.clone(), &mut trie.root, 0).unwrap_err(),
-            TrieError::InvalidCharacter
+            trie.insert(&word).unwrap_err(),
+            TrieError::InvalidCharacter {
+                word: word.clone(),
+                character: '~',
+                position: 0,
+            }
+        );
+
+        assert_eq!(
+            trie.insert("café").unwrap_err(),
+            TrieError::InvalidCharacter {
+                word: "café".to_string(),
+                character: 'é',
+                position: 3,
+            }
         );
 
         Ok(())
@@ -1,11 +1,30 @@
 pub mod common {
     pub mod app_builder;
     pub mod autocomplete;
+    pub mod dictionary;
+    pub mod filter;
+    pub mod infix;
+    pub mod pattern;
+    pub mod phonetic;
+    pub mod ranking;
+    pub mod shortcodes;
+    pub mod snippets;
+    pub mod spellcheck;
+    pub mod stemmer;
+    pub mod thesaurus;
+    pub mod tokenizer;
     pub mod trie;
 }
 pub mod util {
     pub mod app_data;
     pub mod backup;
+    pub mod compression;
+    pub mod encryption;
     pub mod errors;
+    pub mod keymap;
+    pub mod renderer;
     pub mod repl;
+    #[cfg(test)]
+    pub(crate) mod scratch;
+    pub mod theme;
 }
@@ -1,10 +1,29 @@
-use std::error::Error;
+use std::process::ExitCode;
 
+use arbor_cli::common::app_builder::{Arbor, Args};
 use arbor_cli::util::repl::Repl;
+use clap::Parser;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    Repl::new().await?.run().await?;
+async fn main() -> ExitCode {
+    let mut args = Args::parse();
 
-    Ok(())
+    let result = match args.command.take() {
+        Some(command) => command.run(args).await,
+        None => match Arbor::build_with_args(args).await {
+            Ok(arbor) => match Repl::build(arbor).await {
+                Ok(mut repl) => repl.run().await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        },
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("arbor: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
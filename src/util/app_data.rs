@@ -1,12 +1,22 @@
-use std::error::Error;
 use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::common::phonetic::PhoneticAlgorithm;
+
+use super::errors::{ArborError, ConfigError, StorageError};
 
 const APP_DATA_DIR_RELATIVE_PATH: &str = ".local/share/arbor";
+const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 
 pub struct AppData {
     language: String,
     thread_count: u8,
     max_suggestion: u8,
+    /// How long it takes an unused word's recency weight to halve.
+    half_life: Duration,
+    /// How sound-alike words are found; depends on the language by default.
+    phonetic: Option<PhoneticAlgorithm>,
 }
 
 impl Default for AppData {
@@ -15,6 +25,8 @@ impl Default for AppData {
             language: "en-US".to_string(),
             thread_count: 2,
             max_suggestion: 10,
+            half_life: DEFAULT_HALF_LIFE,
+            phonetic: PhoneticAlgorithm::for_language("en-US"),
         }
     }
 }
@@ -24,10 +36,11 @@ impl AppData {
         language: Option<String>,
         thread_count: Option<u8>,
         max_suggestion: Option<u8>,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, ArborError> {
         let mut app_data = AppData::default();
 
         if let Some(lang) = language {
+            app_data.phonetic = PhoneticAlgorithm::for_language(&lang);
             app_data.language = lang;
         }
 
@@ -39,13 +52,14 @@ impl AppData {
             app_data.max_suggestion = max_sugg;
         }
 
-        // Resolve the home directory
-        let home_dir = dirs::home_dir().ok_or("Unable to find home directory")?;
-        let app_data_dir = home_dir.join(APP_DATA_DIR_RELATIVE_PATH);
+        let app_data_dir = app_data_dir()?;
 
         // Create the directory if it doesn't exist
         if !app_data_dir.exists() {
-            fs::create_dir_all(&app_data_dir)?;
+            fs::create_dir_all(&app_data_dir).map_err(|source| StorageError::CreateDir {
+                path: app_data_dir.clone(),
+                source,
+            })?;
         }
 
         Ok(app_data)
@@ -62,10 +76,35 @@ impl AppData {
     pub fn get_max_suggestion(&self) -> u8 {
         self.max_suggestion
     }
+
+    pub fn get_half_life(&self) -> Duration {
+        self.half_life
+    }
+
+    pub fn set_half_life(&mut self, half_life: Duration) {
+        self.half_life = half_life;
+    }
+
+    pub fn get_phonetic(&self) -> Option<PhoneticAlgorithm> {
+        self.phonetic
+    }
+
+    pub fn set_phonetic(&mut self, phonetic: Option<PhoneticAlgorithm>) {
+        self.phonetic = phonetic;
+    }
+}
+
+pub fn app_data_dir() -> Result<PathBuf, ConfigError> {
+    // Resolve the home directory
+    let home_dir = dirs::home_dir().ok_or(ConfigError::HomeDirNotFound)?;
+
+    Ok(home_dir.join(APP_DATA_DIR_RELATIVE_PATH))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::error::Error;
+
     use super::*;
 
     #[test]
@@ -75,12 +114,18 @@ mod tests {
         assert_eq!(app_data.get_language(), "en-US".to_string());
         assert_eq!(app_data.get_thread_count(), 2);
         assert_eq!(app_data.get_max_suggestion(), 10);
+        assert_eq!(app_data.get_half_life(), DEFAULT_HALF_LIFE);
+        assert_eq!(
+            app_data.get_phonetic(),
+            Some(PhoneticAlgorithm::DoubleMetaphone)
+        );
 
         let app_data = AppData::build(Some("tr-TR".to_string()), Some(4), Some(5))?;
 
         assert_eq!(app_data.get_language(), "tr-TR".to_string());
         assert_eq!(app_data.get_thread_count(), 4);
         assert_eq!(app_data.get_max_suggestion(), 5);
+        assert_eq!(app_data.get_phonetic(), None);
 
         let home_dir = dirs::home_dir().unwrap();
         let app_data_dir = home_dir.join(APP_DATA_DIR_RELATIVE_PATH);
@@ -1,71 +1,689 @@
-use std::error::Error;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::fs::{File, OpenOptions};
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
-use tokio::sync::Mutex;
+use tokio::io::{
+    AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::sync::{mpsc, Mutex};
+
+use super::compression::Compression;
+use super::encryption::{self, Cipher, KeySource, HEADER_PREFIX};
+use super::errors::StorageError;
 
 // TODO: find out how to switch back to this path => "/var/lib/arbor/backup"
 // in production mode
 const BACKUP_FILE_PATH: &str = "/tmp/arbor/backup";
 
+/// Size past which the backup is rotated into a compressed segment.
+pub const DEFAULT_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+/// Lines sealed together, so no sealed line grows past what a reader holds at once.
+const SEALED_BATCH: usize = 1024;
+
+/// Lines a segment is decompressed ahead of the records being read.
+const SEGMENT_LINES: usize = 1024;
+
+/// Learned words as `word<TAB>timestamp` lines. An encrypted backup starts
+/// with a header line instead and holds one sealed line per write, so it has
+/// to be unlocked with its key before it can be read or written.
+///
+/// With compression on, the file is moved into a numbered segment next to it,
+/// e.g. `backup.3.zst`, each time it outgrows the segment size. Segments are
+/// read oldest first, before the file itself.
 pub struct Backup {
     pub file_path: String,
     file: Arc<Mutex<File>>,
+    encrypted: bool,
+    cipher: Option<Cipher>,
+    /// Sealed lines in the file, so the next one gets the following sequence number.
+    sealed: AtomicU64,
+    compression: Compression,
+    segment_size: u64,
 }
 
 impl Backup {
-    pub async fn build(file_path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+    pub async fn build(file_path: Option<&str>) -> Result<Self, StorageError> {
         let path = std::path::Path::new(match file_path {
             Some(path) => path,
             None => BACKUP_FILE_PATH,
         });
 
         if !path.exists() {
-            let prefix = path.parent().unwrap();
-            std::fs::create_dir_all(prefix).unwrap();
+            if let Some(prefix) = path.parent() {
+                std::fs::create_dir_all(prefix).map_err(|source| StorageError::CreateDir {
+                    path: prefix.to_path_buf(),
+                    source,
+                })?;
+            }
         }
 
-        let file = OpenOptions::new()
-            .append(true)
-            .read(true)
-            .create(true)
-            .open(path)
-            .await
-            .expect("Could not open file");
+        let mut file = open(path).await?;
+        let mut prefix = vec![0; HEADER_PREFIX.len()];
+        let encrypted =
+            file.read_exact(&mut prefix).await.is_ok() && prefix == HEADER_PREFIX.as_bytes();
 
         Ok(Self {
-            file_path: path.to_str().unwrap().to_owned(),
+            file_path: path.to_string_lossy().into_owned(),
             file: Arc::new(Mutex::new(file)),
+            encrypted,
+            cipher: None,
+            sealed: AtomicU64::new(0),
+            compression: Compression::None,
+            segment_size: DEFAULT_SEGMENT_SIZE,
         })
     }
 
-    pub async fn save_data(&self, words: Vec<String>) -> Result<(), Box<dyn Error>> {
-        let mut file = self.file.lock().await;
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// Sets how segments and snapshots are compressed from now on. Existing
+    /// segments are read whatever they were compressed with.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segment_size = segment_size;
+    }
+
+    /// Opens an encrypted backup with the key from `source`, or encrypts a
+    /// plain one with it. Fails with [`StorageError::WrongKey`] if the key
+    /// doesn't match the one the backup was encrypted with.
+    pub async fn unlock(&mut self, source: &KeySource) -> Result<(), StorageError> {
+        if !self.encrypted {
+            return self.rotate(source).await;
+        }
+
+        let file = self.file.clone();
+        let mut file = file.lock().await;
+        let read_error = |source| StorageError::Read {
+            path: PathBuf::from(&self.file_path),
+            source,
+        };
+
+        file.seek(std::io::SeekFrom::Start(0))
+            .await
+            .map_err(read_error)?;
+
+        let mut reader = BufReader::new(&mut *file);
+        let mut header = String::new();
+        reader.read_line(&mut header).await.map_err(read_error)?;
+
+        let mut lines = reader.lines();
+        let mut sealed = 0;
 
-        for word in words {
-            file.write_all(word.as_bytes()).await?;
-            file.write_all(b"\n").await?;
+        while let Some(line) = lines.next_line().await.map_err(read_error)? {
+            if !line.is_empty() {
+                sealed += 1;
+            }
         }
 
+        self.cipher =
+            Some(Cipher::open(header.trim_end(), source, Path::new(&self.file_path)).await?);
+        self.sealed.store(sealed, Ordering::Relaxed);
+
         Ok(())
     }
 
-    pub async fn load_data(&self) -> Result<Vec<String>, Box<dyn Error>> {
+    /// Re-encrypts every record with a key from `source` and a fresh salt.
+    /// The backup must be unlocked, or plain.
+    pub async fn rotate(&mut self, source: &KeySource) -> Result<(), StorageError> {
+        let file = self.file.clone();
+        let mut file = file.lock().await;
+        let lines = self.read_lines(&mut file).await?;
+
+        self.cipher = Some(Cipher::create(source).await?);
+        self.encrypted = true;
+
+        self.replace(&mut file, lines).await
+    }
+
+    pub async fn save_data(&self, words: Vec<String>) -> Result<(), StorageError> {
+        self.write_lines(words).await
+    }
+
+    /// Saves each word along with when it was used, as `word<TAB>timestamp`.
+    pub async fn save_records(&self, records: Vec<(String, u64)>) -> Result<(), StorageError> {
+        self.write_lines(
+            records
+                .into_iter()
+                .map(|(word, timestamp)| format!("{}\t{}", word, timestamp))
+                .collect(),
+        )
+        .await
+    }
+
+    pub async fn load_data(&self) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .load_records()
+            .await?
+            .into_iter()
+            .map(|(word, _)| word)
+            .collect())
+    }
+
+    /// Loads every saved word with its timestamp, if the line has one.
+    pub async fn load_records(&self) -> Result<Vec<(String, Option<u64>)>, StorageError> {
+        let mut records = Vec::new();
+
+        self.for_each_record(|word, timestamp| {
+            records.push((word, timestamp));
+
+            Ok::<(), StorageError>(())
+        })
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Calls `f` with every saved word and its timestamp, oldest first.
+    /// Segments are decompressed as they're read, so only a line of the
+    /// backup is held at a time, however large it is.
+    pub async fn for_each_record<E>(
+        &self,
+        mut f: impl FnMut(String, Option<u64>) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        E: From<StorageError>,
+    {
         let mut file = self.file.lock().await;
 
-        file.seek(std::io::SeekFrom::Start(0)).await?;
+        self.for_each_line(&mut file, |line| match line.split_once('\t') {
+            Some((word, timestamp)) => f(word.to_string(), timestamp.parse::<u64>().ok()),
+            None => f(line, None),
+        })
+        .await
+    }
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).await?;
+    /// Rewrites the backup with only the lines whose word `keep` accepts,
+    /// returning the words of the records dropped.
+    pub async fn retain(&self, keep: impl Fn(&str) -> bool) -> Result<Vec<String>, StorageError> {
+        let mut file = self.file.lock().await;
+
+        let (kept, dropped): (Vec<_>, Vec<_>) = self
+            .read_lines(&mut file)
+            .await?
+            .into_iter()
+            .partition(|line| keep(line.split('\t').next().unwrap_or_default()));
+
+        if dropped.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let lines: Vec<String> = contents.lines().map(|line| line.to_string()).collect();
+        self.replace(&mut file, kept).await?;
+
+        Ok(dropped
+            .into_iter()
+            .map(|line| match line.split_once('\t') {
+                Some((word, _)) => word.to_string(),
+                None => line,
+            })
+            .collect())
+    }
+
+    async fn read_lines(&self, file: &mut File) -> Result<Vec<String>, StorageError> {
+        let mut lines = Vec::new();
+
+        self.for_each_line(file, |line| {
+            lines.push(line);
+
+            Ok::<(), StorageError>(())
+        })
+        .await?;
 
         Ok(lines)
     }
+
+    /// Calls `f` with every record line of the segments and then the file,
+    /// decrypted if the backup is encrypted.
+    async fn for_each_line<E>(
+        &self,
+        file: &mut File,
+        mut f: impl FnMut(String) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        E: From<StorageError>,
+    {
+        for (number, segment) in self.segments().await? {
+            let read_error = |source| StorageError::Read {
+                path: segment.clone(),
+                source,
+            };
+
+            let mut lines = segment_lines(segment.clone());
+            let mut record = 0;
+            let mut sealed = 0;
+
+            while let Some(line) = lines.recv().await {
+                let line = line.map_err(read_error)?;
+                record += 1;
+
+                // NOTE: its records are in the files that replaced it
+                if record == 1 && self.is_stale(&line)? {
+                    break;
+                }
+
+                self.open_line(&segment, number, record, &mut sealed, line, &mut f)?;
+            }
+        }
+
+        self.for_each_file_line(file, f).await
+    }
+
+    /// Calls `f` with every record line of the file alone, decrypted if the
+    /// backup is encrypted.
+    async fn for_each_file_line<E>(
+        &self,
+        file: &mut File,
+        mut f: impl FnMut(String) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        E: From<StorageError>,
+    {
+        let path = PathBuf::from(&self.file_path);
+        let read_error = |source| StorageError::Read {
+            path: path.clone(),
+            source,
+        };
+
+        file.seek(std::io::SeekFrom::Start(0))
+            .await
+            .map_err(read_error)?;
+
+        let mut lines = BufReader::new(&mut *file).lines();
+        let mut record = 0;
+        let mut sealed = 0;
+
+        while let Some(line) = lines.next_line().await.map_err(read_error)? {
+            record += 1;
+            self.open_line(&path, 0, record, &mut sealed, line, &mut f)?;
+        }
+
+        Ok(())
+    }
+
+    /// Passes the records of `line`, the `record`th line of `path`, to `f`.
+    /// `path` is segment `segment`, or the file itself when it's 0, and
+    /// `sealed` counts its sealed lines opened so far.
+    fn open_line<E>(
+        &self,
+        path: &Path,
+        segment: u64,
+        record: usize,
+        sealed: &mut u64,
+        line: String,
+        f: &mut impl FnMut(String) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        E: From<StorageError>,
+    {
+        if !self.encrypted {
+            return f(line);
+        }
+
+        if line.is_empty() || encryption::is_header(&line) {
+            return Ok(());
+        }
+
+        let sequence = *sealed;
+        *sealed += 1;
+
+        let plaintext = self
+            .cipher()?
+            .open_line(segment, sequence, &line)
+            .and_then(|plaintext| String::from_utf8(plaintext).ok())
+            .ok_or_else(|| StorageError::Tampered {
+                path: path.to_path_buf(),
+                record,
+            })?;
+
+        for line in plaintext.lines() {
+            f(line.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether a segment starting with `first_line` was left behind by a
+    /// rewrite that stopped before removing it. Every segment written since
+    /// starts with the file's header, or none when the backup is plain.
+    fn is_stale(&self, first_line: &str) -> Result<bool, StorageError> {
+        match (self.encrypted, encryption::is_header(first_line)) {
+            (true, true) => Ok(first_line != self.cipher()?.header()),
+            (false, false) => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn cipher(&self) -> Result<&Cipher, StorageError> {
+        self.cipher.as_ref().ok_or_else(|| StorageError::Locked {
+            path: PathBuf::from(&self.file_path),
+        })
+    }
+
+    /// The backup's segments with their numbers, oldest first.
+    async fn segments(&self) -> Result<Vec<(u64, PathBuf)>, StorageError> {
+        let path = Path::new(&self.file_path);
+        let (Some(directory), Some(name)) = (path.parent(), path.file_name()) else {
+            return Ok(Vec::new());
+        };
+        let directory = match directory.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => directory,
+        };
+        let name = name.to_string_lossy();
+
+        let read_error = |source| StorageError::Read {
+            path: directory.to_path_buf(),
+            source,
+        };
+
+        let mut entries = tokio::fs::read_dir(directory).await.map_err(read_error)?;
+        let mut segments: Vec<(u64, PathBuf)> = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await.map_err(read_error)? {
+            let file_name = entry.file_name();
+            let segment = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(name.as_ref()))
+                .and_then(|suffix| suffix.strip_prefix('.'))
+                .and_then(|suffix| suffix.split_once('.'))
+                .filter(|(_, extension)| matches!(*extension, "gz" | "zst"))
+                .and_then(|(number, _)| number.parse().ok());
+
+            if let Some(number) = segment {
+                segments.push((number, entry.path()));
+            }
+        }
+
+        segments.sort();
+
+        Ok(segments)
+    }
+
+    /// Swaps the whole backup for one holding only `lines`. With compression
+    /// on, they go to a single snapshot segment and the file is left empty.
+    /// Old segments are removed last; until then, those under another header
+    /// are skipped as stale.
+    async fn replace(&self, file: &mut File, lines: Vec<String>) -> Result<(), StorageError> {
+        let segments = self.segments().await?;
+
+        if self.compression.extension().is_some() && !lines.is_empty() {
+            let next = segments.last().map_or(1, |(number, _)| number + 1);
+            let snapshot = self.snapshot(next, lines).await?;
+
+            self.write_segment(next, std::io::Cursor::new(snapshot))
+                .await?;
+            self.write_file(file, Vec::new()).await?;
+        } else {
+            self.write_file(file, lines).await?;
+        }
+
+        // NOTE: old segments go last so a crash leaves records twice rather than lost
+        for (_, segment) in segments {
+            tokio::fs::remove_file(&segment)
+                .await
+                .map_err(|source| StorageError::Write {
+                    path: segment.clone(),
+                    source,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the file into a new segment once it outgrows the segment size.
+    async fn rotate_segment(&self, file: &mut File) -> Result<(), StorageError> {
+        let path = PathBuf::from(&self.file_path);
+        let read_error = |source| StorageError::Read {
+            path: path.clone(),
+            source,
+        };
+
+        if self.compression.extension().is_none()
+            || file.metadata().await.map_err(read_error)?.len() < self.segment_size
+        {
+            return Ok(());
+        }
+
+        let next = self
+            .segments()
+            .await?
+            .last()
+            .map_or(1, |(number, _)| number + 1);
+
+        if self.encrypted {
+            // NOTE: sealed lines are bound to the segment they're in, so seal them again
+            let mut lines = Vec::new();
+
+            self.for_each_file_line(file, |line| {
+                lines.push(line);
+
+                Ok::<(), StorageError>(())
+            })
+            .await?;
+
+            let snapshot = self.snapshot(next, lines).await?;
+
+            self.write_segment(next, std::io::Cursor::new(snapshot))
+                .await?;
+        } else {
+            // NOTE: the clone shares the file's offset, left at the end by the last write
+            let mut contents = file.try_clone().await.map_err(read_error)?;
+
+            contents
+                .seek(std::io::SeekFrom::Start(0))
+                .await
+                .map_err(read_error)?;
+
+            self.write_segment(next, contents.into_std().await).await?;
+        }
+
+        self.write_file(file, Vec::new()).await
+    }
+
+    /// The contents of segment `number` holding only `lines`, under the
+    /// header if the backup is encrypted.
+    async fn snapshot(&self, number: u64, lines: Vec<String>) -> Result<Vec<u8>, StorageError> {
+        let mut snapshot = Vec::new();
+
+        if let Some(cipher) = &self.cipher {
+            snapshot.extend(format!("{}\n", cipher.header()).into_bytes());
+        }
+
+        self.write_to(&mut snapshot, lines, number, 0).await?;
+
+        Ok(snapshot)
+    }
+
+    /// Compresses everything `contents` yields into segment `number`, on a
+    /// blocking thread.
+    async fn write_segment(
+        &self,
+        number: u64,
+        contents: impl Read + Send + 'static,
+    ) -> Result<(), StorageError> {
+        let extension = self.compression.extension().unwrap_or("gz");
+        let segment = PathBuf::from(format!("{}.{}.{}", self.file_path, number, extension));
+        let staged_path = PathBuf::from(format!("{}.tmp", segment.display()));
+        let write_error = |source| StorageError::Write {
+            path: staged_path.clone(),
+            source,
+        };
+
+        let _ = tokio::fs::remove_file(&staged_path).await;
+        let staged = open(&staged_path).await?.into_std().await;
+        let compression = self.compression;
+
+        tokio::task::spawn_blocking(move || {
+            compression
+                .compress_stream(contents, std::io::BufWriter::new(staged))
+                .and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+                .and_then(|staged| staged.sync_all())
+        })
+        .await
+        .map_err(std::io::Error::from)
+        .and_then(|result| result)
+        .map_err(write_error)?;
+
+        tokio::fs::rename(&staged_path, &segment)
+            .await
+            .map_err(write_error)
+    }
+
+    /// Swaps the file for one holding only `lines`. They are written next to
+    /// it first, so a crash halfway through leaves the old file whole.
+    async fn write_file(&self, file: &mut File, lines: Vec<String>) -> Result<(), StorageError> {
+        let path = Path::new(&self.file_path);
+        let staged_path = PathBuf::from(format!("{}.tmp", self.file_path));
+        let write_error = |source| StorageError::Write {
+            path: staged_path.clone(),
+            source,
+        };
+
+        let _ = tokio::fs::remove_file(&staged_path).await;
+        let mut staged = open(&staged_path).await?;
+
+        if let Some(cipher) = &self.cipher {
+            let header = format!("{}\n", cipher.header());
+
+            staged
+                .write_all(header.as_bytes())
+                .await
+                .map_err(write_error)?;
+        }
+
+        let sealed = self.write_to(&mut staged, lines, 0, 0).await?;
+        staged.sync_all().await.map_err(write_error)?;
+
+        tokio::fs::rename(&staged_path, path)
+            .await
+            .map_err(write_error)?;
+
+        *file = open(path).await?;
+        self.sealed.store(sealed, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    async fn write_lines(&self, lines: Vec<String>) -> Result<(), StorageError> {
+        let mut file = self.file.lock().await;
+        let sealed = self
+            .write_to(&mut *file, lines, 0, self.sealed.load(Ordering::Relaxed))
+            .await?;
+
+        self.sealed.fetch_add(sealed, Ordering::Relaxed);
+        self.rotate_segment(&mut file).await
+    }
+
+    /// Writes `lines` to `writer`, sealing them first if the backup is
+    /// encrypted, as lines of segment `segment` numbered on from `sequence`.
+    /// Returns the sealed lines written.
+    async fn write_to(
+        &self,
+        writer: &mut (impl AsyncWrite + Unpin),
+        lines: Vec<String>,
+        segment: u64,
+        sequence: u64,
+    ) -> Result<u64, StorageError> {
+        let write_error = |source| StorageError::Write {
+            path: PathBuf::from(&self.file_path),
+            source,
+        };
+
+        let lines = match self.encrypted {
+            // NOTE: lines are sealed in batches so each costs one nonce and tag
+            true => {
+                let cipher = self.cipher()?;
+
+                lines
+                    .chunks(SEALED_BATCH)
+                    .zip(sequence..)
+                    .map(|(batch, sequence)| {
+                        cipher.seal(segment, sequence, batch.join("\n").as_bytes())
+                    })
+                    .collect()
+            }
+            false => lines,
+        };
+        let sealed = match self.encrypted {
+            true => lines.len() as u64,
+            false => 0,
+        };
+
+        for line in lines {
+            let line = format!("{}\n", line);
+
+            writer
+                .write_all(line.as_bytes())
+                .await
+                .map_err(write_error)?;
+        }
+
+        writer.flush().await.map_err(write_error)?;
+
+        Ok(sealed)
+    }
+}
+
+/// Decompresses `segment` on a blocking thread, handing its lines over as
+/// they're read. Reading stops at the first error, or once the receiver is dropped.
+fn segment_lines(segment: PathBuf) -> mpsc::Receiver<std::io::Result<String>> {
+    let (sender, receiver) = mpsc::channel(SEGMENT_LINES);
+
+    tokio::task::spawn_blocking(move || {
+        let lines = std::fs::File::open(&segment)
+            .and_then(|file| Compression::decoder(std::io::BufReader::new(file)))
+            .map(|reader| reader.lines());
+
+        let lines = match lines {
+            Ok(lines) => lines,
+            Err(error) => {
+                let _ = sender.blocking_send(Err(error));
+                return;
+            }
+        };
+
+        for line in lines {
+            let failed = line.is_err();
+
+            if sender.blocking_send(line).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    receiver
+}
+
+async fn open(path: &Path) -> Result<File, StorageError> {
+    let mut options = OpenOptions::new();
+    options.append(true).read(true).create(true);
+
+    // NOTE: learned words can be personal, keep new files private to the user
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options
+        .open(path)
+        .await
+        .map_err(|source| StorageError::Open {
+            path: path.to_path_buf(),
+            source,
+        })
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::util::scratch::Scratch;
+
     use super::*;
 
     async fn init_backup(file_path: Option<&str>) -> Backup {
@@ -98,4 +716,314 @@ mod tests {
 
         std::fs::remove_file(backup.file_path).unwrap();
     }
+
+    #[tokio::test]
+    async fn it_saves_records_with_timestamps() {
+        let backup = init_backup(Some("records")).await;
+
+        backup
+            .save_records(Vec::from([("hello".to_string(), 1700000000)]))
+            .await
+            .unwrap();
+        backup
+            .save_data(Vec::from(["world".to_string()]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            backup.load_records().await.unwrap(),
+            Vec::from([
+                ("hello".to_string(), Some(1700000000)),
+                ("world".to_string(), None),
+            ])
+        );
+        assert_eq!(backup.load_data().await.unwrap(), ["hello", "world"]);
+
+        std::fs::remove_file(backup.file_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_retains_matching_records() {
+        let backup = init_backup(Some("retained")).await;
+
+        backup
+            .save_records(Vec::from([
+                ("hello".to_string(), 1700000000),
+                ("secret".to_string(), 1700000001),
+            ]))
+            .await
+            .unwrap();
+        backup
+            .save_data(Vec::from(["secret".to_string(), "world".to_string()]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            backup.retain(|word| word != "secret").await.unwrap(),
+            ["secret", "secret"]
+        );
+
+        backup
+            .save_data(Vec::from(["again".to_string()]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&backup.file_path).unwrap(),
+            "hello\t1700000000\nworld\nagain\n"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&backup.file_path)
+                .unwrap()
+                .permissions()
+                .mode();
+
+            assert_eq!(mode & 0o077, 0, "backup is private to the user");
+        }
+
+        std::fs::remove_file(backup.file_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_encrypts_and_rotates_the_key() {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+        let passphrase = KeySource::Passphrase("correct horse".to_string());
+
+        let mut backup = init_backup(path.to_str()).await;
+
+        backup
+            .save_records(Vec::from([("hello".to_string(), 1700000000)]))
+            .await
+            .unwrap();
+        backup.unlock(&passphrase).await.unwrap();
+        backup
+            .save_data(Vec::from(["world".to_string()]))
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+
+        assert!(contents.starts_with(HEADER_PREFIX));
+        assert!(!contents.contains("hello") && !contents.contains("world"));
+
+        let mut reopened = init_backup(path.to_str()).await;
+
+        assert!(reopened.is_encrypted());
+        assert!(matches!(
+            reopened.load_data().await,
+            Err(StorageError::Locked { .. })
+        ));
+        assert!(matches!(
+            reopened
+                .unlock(&KeySource::Passphrase("wrong".to_string()))
+                .await,
+            Err(StorageError::WrongKey { .. })
+        ));
+
+        reopened.unlock(&passphrase).await.unwrap();
+
+        assert_eq!(reopened.load_data().await.unwrap(), ["hello", "world"]);
+
+        let key_path = scratch.path("key");
+        std::fs::write(&key_path, [7; 32]).unwrap();
+        let key_file = KeySource::File(key_path.clone());
+
+        reopened.rotate(&key_file).await.unwrap();
+
+        let mut rotated = init_backup(path.to_str()).await;
+
+        assert!(matches!(
+            rotated.unlock(&passphrase).await,
+            Err(StorageError::WrongKey { .. })
+        ));
+
+        rotated.unlock(&key_file).await.unwrap();
+
+        assert_eq!(
+            rotated.load_records().await.unwrap(),
+            Vec::from([
+                ("hello".to_string(), Some(1700000000)),
+                ("world".to_string(), None),
+            ])
+        );
+
+        rotated
+            .save_data(Vec::from(["again".to_string()]))
+            .await
+            .unwrap();
+
+        let mut lines = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        lines.swap(1, 2);
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let mut reordered = init_backup(path.to_str()).await;
+        reordered.unlock(&key_file).await.unwrap();
+
+        assert!(matches!(
+            reordered.load_data().await,
+            Err(StorageError::Tampered { record: 2, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_swapped_and_replayed_segments() {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+        let key_path = scratch.path("key");
+        std::fs::write(&key_path, [7; 32]).unwrap();
+        let key_file = KeySource::File(key_path);
+
+        let mut backup = init_backup(path.to_str()).await;
+        backup.set_compression(Compression::Gzip);
+        backup.set_segment_size(64);
+        backup.unlock(&key_file).await.unwrap();
+
+        for batch in 0..2 {
+            let words = (0..8).map(|i| format!("word{}", batch * 8 + i)).collect();
+
+            backup.save_data(words).await.unwrap();
+        }
+
+        assert_eq!(backup.load_data().await.unwrap().len(), 16);
+
+        let segments = backup
+            .segments()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, segment)| segment)
+            .collect::<Vec<PathBuf>>();
+        let contents = segments
+            .iter()
+            .map(|segment| std::fs::read(segment).unwrap())
+            .collect::<Vec<Vec<u8>>>();
+
+        assert_eq!(segments.len(), 2);
+
+        std::fs::write(&segments[0], &contents[1]).unwrap();
+        std::fs::write(&segments[1], &contents[0]).unwrap();
+
+        assert!(matches!(
+            backup.load_data().await,
+            Err(StorageError::Tampered { path, record: 2 }) if path == segments[0]
+        ));
+
+        std::fs::write(&segments[1], &contents[1]).unwrap();
+        std::fs::write(&segments[0], &contents[0]).unwrap();
+        std::fs::write(scratch.path("backup.3.gz"), &contents[0]).unwrap();
+
+        assert!(matches!(
+            backup.load_data().await,
+            Err(StorageError::Tampered { path, record: 2 }) if path == scratch.path("backup.3.gz")
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_skips_segments_left_by_an_interrupted_rotation() {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+        let old_key = KeySource::Passphrase("old".to_string());
+        let new_key = KeySource::Passphrase("new".to_string());
+
+        let mut backup = init_backup(path.to_str()).await;
+        backup.set_compression(Compression::Gzip);
+        backup.set_segment_size(64);
+        backup.unlock(&old_key).await.unwrap();
+
+        for batch in 0..4 {
+            let words = (0..8).map(|i| format!("word{}", batch * 8 + i)).collect();
+
+            backup.save_data(words).await.unwrap();
+        }
+
+        let words = backup.load_data().await.unwrap();
+        let old_files = backup
+            .segments()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, segment)| segment)
+            .chain([path.clone()])
+            .map(|file| (file.clone(), std::fs::read(file).unwrap()))
+            .collect::<Vec<(PathBuf, Vec<u8>)>>();
+
+        backup.rotate(&new_key).await.unwrap();
+
+        // NOTE: as if it stopped before removing the old segments
+        for (file, contents) in &old_files[..old_files.len() - 1] {
+            std::fs::write(file, contents).unwrap();
+        }
+
+        let mut reopened = init_backup(path.to_str()).await;
+        reopened.unlock(&new_key).await.unwrap();
+
+        assert_eq!(reopened.load_data().await.unwrap(), words);
+
+        // NOTE: as if it stopped before swapping the file, with the old key still in use
+        for (file, contents) in &old_files {
+            std::fs::write(file, contents).unwrap();
+        }
+
+        let mut reopened = init_backup(path.to_str()).await;
+        reopened.unlock(&old_key).await.unwrap();
+
+        assert_eq!(reopened.load_data().await.unwrap(), words);
+    }
+
+    #[tokio::test]
+    async fn it_rotates_compressed_segments() {
+        let scratch = Scratch::new();
+        let path = scratch.path("backup");
+
+        let mut backup = init_backup(path.to_str()).await;
+        backup.set_compression(Compression::Zstd);
+        backup.set_segment_size(32);
+
+        for batch in 0..3 {
+            let words = (0..8).map(|i| format!("word{}", batch * 8 + i)).collect();
+
+            backup.save_data(words).await.unwrap();
+            backup.set_compression(Compression::Gzip);
+        }
+
+        backup
+            .save_data(Vec::from(["tail".to_string()]))
+            .await
+            .unwrap();
+
+        let segments = backup.segments().await.unwrap();
+
+        assert_eq!(segments.len(), 3);
+        assert!(segments[0].1.ends_with("backup.1.zst"));
+        assert!(segments[2].1.ends_with("backup.3.gz"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "tail\n");
+
+        let words = backup.load_data().await.unwrap();
+
+        assert_eq!(words.len(), 25);
+        assert_eq!(words.first().unwrap(), "word0");
+        assert_eq!(words.last().unwrap(), "tail");
+
+        backup.retain(|word| word != "word3").await.unwrap();
+
+        let segments = backup.segments().await.unwrap();
+
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].1.ends_with("backup.4.gz"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        assert_eq!(backup.load_data().await.unwrap().len(), 24);
+
+        // NOTE: nothing to drop leaves the snapshot in place
+        assert!(backup.retain(|_| true).await.unwrap().is_empty());
+        assert_eq!(backup.segments().await.unwrap(), segments);
+    }
 }
@@ -0,0 +1,335 @@
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use super::errors::StorageError;
+
+/// Starts the first line of an encrypted backup, followed by how the key is
+/// derived, the salt and an encrypted check value.
+pub const HEADER_PREFIX: &str = "#arbor-encrypted v1";
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const SALT_LENGTH: usize = 16;
+
+/// scrypt cost as a power of two, i.e. 32 MiB of memory per derivation.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Encrypted in the header so a wrong key is caught before any record is read.
+const CHECK: &[u8] = b"arbor";
+
+const KDFS: [&str; 2] = ["key-file", "scrypt"];
+
+/// Where the backup key comes from.
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    /// A file holding 32 raw bytes or 64 hex digits.
+    File(PathBuf),
+    /// A passphrase stretched into a key with scrypt.
+    Passphrase(String),
+}
+
+impl KeySource {
+    fn kdf(&self) -> &'static str {
+        match self {
+            KeySource::File(_) => "key-file",
+            KeySource::Passphrase(_) => "scrypt",
+        }
+    }
+
+    async fn key(&self, salt: &[u8]) -> Result<[u8; KEY_LENGTH], StorageError> {
+        let mut key = [0; KEY_LENGTH];
+
+        match self {
+            KeySource::File(path) => {
+                let contents =
+                    tokio::fs::read(path)
+                        .await
+                        .map_err(|source| StorageError::Read {
+                            path: path.clone(),
+                            source,
+                        })?;
+                let invalid = || StorageError::InvalidKey { path: path.clone() };
+
+                if contents.len() == KEY_LENGTH {
+                    key.copy_from_slice(&contents);
+                } else {
+                    let hex = std::str::from_utf8(&contents).map_err(|_| invalid())?;
+                    let bytes = from_hex(hex.trim()).ok_or_else(invalid)?;
+
+                    if bytes.len() != KEY_LENGTH {
+                        return Err(invalid());
+                    }
+
+                    key.copy_from_slice(&bytes);
+                }
+            }
+            KeySource::Passphrase(passphrase) => {
+                let passphrase = passphrase.clone();
+                let salt = salt.to_vec();
+
+                // NOTE: scrypt is slow by design, so it stays off the runtime's workers
+                key = tokio::task::spawn_blocking(move || {
+                    let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, KEY_LENGTH)
+                        .expect("scrypt parameters are valid");
+
+                    scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key)
+                        .expect("key length is valid");
+
+                    key
+                })
+                .await
+                .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()));
+            }
+        }
+
+        Ok(key)
+    }
+}
+
+/// Seals backup lines with ChaCha20-Poly1305. Each sealed line is the hex of
+/// a random nonce followed by the ciphertext and its tag, so a changed line
+/// fails to open instead of turning into garbage words. The header, the
+/// number of the segment holding the line and its sequence number there are
+/// authenticated along with it, so lines can't be moved, reordered or dropped
+/// from the middle, nor whole segments swapped or replayed, unnoticed.
+pub struct Cipher {
+    aead: ChaCha20Poly1305,
+    header: String,
+}
+
+impl Cipher {
+    /// A cipher with a fresh salt for `source`, to start a new encrypted file.
+    pub async fn create(source: &KeySource) -> Result<Self, StorageError> {
+        let mut salt = [0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+
+        let aead = ChaCha20Poly1305::new(Key::from_slice(&source.key(&salt).await?));
+        let check = seal(&aead, CHECK, &[]);
+
+        Ok(Self {
+            aead,
+            header: format!(
+                "{} {} {} {}",
+                HEADER_PREFIX,
+                source.kdf(),
+                to_hex(&salt),
+                check
+            ),
+        })
+    }
+
+    /// The cipher of the file at `path` whose first line is `header`, failing
+    /// with [`StorageError::WrongKey`] if `source` doesn't open it.
+    pub async fn open(header: &str, source: &KeySource, path: &Path) -> Result<Self, StorageError> {
+        let corrupt_header = || StorageError::Tampered {
+            path: path.to_path_buf(),
+            record: 1,
+        };
+
+        let fields = header
+            .strip_prefix(HEADER_PREFIX)
+            .ok_or_else(corrupt_header)?
+            .split_whitespace()
+            .collect::<Vec<&str>>();
+
+        let [kdf, salt, check] = fields[..] else {
+            return Err(corrupt_header());
+        };
+
+        if !KDFS.contains(&kdf) {
+            return Err(StorageError::UnknownKdf {
+                path: path.to_path_buf(),
+                kdf: kdf.to_string(),
+            });
+        }
+
+        // NOTE: a passphrase never opens a file locked with a key file, and the reverse
+        if kdf != source.kdf() {
+            return Err(StorageError::WrongKey {
+                path: path.to_path_buf(),
+            });
+        }
+
+        let salt = from_hex(salt).ok_or_else(corrupt_header)?;
+        let aead = ChaCha20Poly1305::new(Key::from_slice(&source.key(&salt).await?));
+
+        match open(&aead, check, &[]) {
+            Some(check) if check == CHECK => Ok(Self {
+                aead,
+                header: header.to_string(),
+            }),
+            _ => Err(StorageError::WrongKey {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+
+    /// The first line of files sealed with this cipher.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Seals `plaintext` as the `sequence`th sealed line, from 0, of segment
+    /// `segment`, or of the backup file itself when it's 0.
+    pub fn seal(&self, segment: u64, sequence: u64, plaintext: &[u8]) -> String {
+        seal(
+            &self.aead,
+            plaintext,
+            &self.associated_data(segment, sequence),
+        )
+    }
+
+    /// The plaintext of the `sequence`th sealed line of segment `segment`, or
+    /// `None` if it was changed, moved or sealed with another key.
+    pub fn open_line(&self, segment: u64, sequence: u64, line: &str) -> Option<Vec<u8>> {
+        open(&self.aead, line, &self.associated_data(segment, sequence))
+    }
+
+    fn associated_data(&self, segment: u64, sequence: u64) -> Vec<u8> {
+        [
+            self.header.as_bytes(),
+            &segment.to_be_bytes(),
+            &sequence.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
+
+pub fn is_header(line: &str) -> bool {
+    line.starts_with(HEADER_PREFIX)
+}
+
+fn seal(aead: &ChaCha20Poly1305, plaintext: &[u8], aad: &[u8]) -> String {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = aead
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("plaintext fits in a single message");
+
+    to_hex(&[nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(aead: &ChaCha20Poly1305, line: &str, aad: &[u8]) -> Option<Vec<u8>> {
+    let bytes = from_hex(line)?;
+
+    if bytes.len() < NONCE_LENGTH {
+        return None;
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+
+    aead.decrypt(
+        Nonce::from_slice(nonce),
+        Payload {
+            msg: ciphertext,
+            aad,
+        },
+    )
+    .ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::util::scratch::Scratch;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_seals_and_opens_lines() -> Result<(), Box<dyn Error>> {
+        let scratch = Scratch::new();
+        let path = scratch.path("key");
+        std::fs::write(&path, "2a".repeat(KEY_LENGTH))?;
+
+        let source = KeySource::File(path.clone());
+        let cipher = Cipher::create(&source).await?;
+        let line = cipher.seal(0, 0, b"hello\t1700000000");
+
+        assert!(is_header(cipher.header()));
+        assert!(!line.contains("hello"));
+        assert_eq!(cipher.open_line(0, 0, &line).unwrap(), b"hello\t1700000000");
+        assert!(
+            cipher.open_line(0, 1, &line).is_none(),
+            "lines can't be moved"
+        );
+        assert!(
+            cipher.open_line(2, 0, &line).is_none(),
+            "lines can't change segment"
+        );
+
+        let mut tampered = line.clone().into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'0' { b'1' } else { b'0' };
+
+        assert!(cipher
+            .open_line(0, 0, &String::from_utf8(tampered)?)
+            .is_none());
+
+        let reopened = Cipher::open(cipher.header(), &source, &path).await?;
+
+        assert_eq!(
+            reopened
+                .open_line(1, 3, &cipher.seal(1, 3, b"again"))
+                .unwrap(),
+            b"again"
+        );
+
+        let other = Cipher::create(&source).await?;
+
+        assert!(
+            other.open_line(0, 0, &line).is_none(),
+            "lines are bound to their header"
+        );
+
+        let unknown = cipher.header().replacen("key-file", "argon2", 1);
+
+        assert!(matches!(
+            Cipher::open(&unknown, &source, &path).await,
+            Err(StorageError::UnknownKdf { .. })
+        ));
+        assert!(matches!(
+            Cipher::open(
+                cipher.header(),
+                &KeySource::Passphrase("hunter2".to_string()),
+                &path
+            )
+            .await,
+            Err(StorageError::WrongKey { .. })
+        ));
+
+        std::fs::write(&path, "too short")?;
+
+        assert!(matches!(
+            Cipher::open(cipher.header(), &source, &path).await,
+            Err(StorageError::InvalidKey { .. })
+        ));
+
+        Ok(())
+    }
+}
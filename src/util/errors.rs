@@ -1,8 +1,99 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, io, path::PathBuf};
+
+use crate::common::tokenizer::Rejection;
+
+/// Every failure the library can report, grouped by the subsystem it comes from.
+#[derive(Debug)]
+pub enum ArborError {
+    Trie(TrieError),
+    Storage(StorageError),
+    Config(ConfigError),
+    Tokenizer(TokenizerError),
+    Repl(ReplError),
+}
+
+impl Error for ArborError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ArborError::Trie(e) => Some(e),
+            ArborError::Storage(e) => Some(e),
+            ArborError::Config(e) => Some(e),
+            ArborError::Tokenizer(e) => Some(e),
+            ArborError::Repl(e) => Some(e),
+        }
+    }
+}
+
+impl Display for ArborError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArborError::Trie(e) => write!(f, "{}", e),
+            ArborError::Storage(e) => write!(f, "{}", e),
+            ArborError::Config(e) => write!(f, "{}", e),
+            ArborError::Tokenizer(e) => write!(f, "{}", e),
+            ArborError::Repl(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<TrieError> for ArborError {
+    fn from(e: TrieError) -> Self {
+        ArborError::Trie(e)
+    }
+}
+
+impl From<StorageError> for ArborError {
+    fn from(e: StorageError) -> Self {
+        ArborError::Storage(e)
+    }
+}
+
+impl From<ConfigError> for ArborError {
+    fn from(e: ConfigError) -> Self {
+        ArborError::Config(e)
+    }
+}
+
+impl From<TokenizerError> for ArborError {
+    fn from(e: TokenizerError) -> Self {
+        ArborError::Tokenizer(e)
+    }
+}
+
+impl From<ReplError> for ArborError {
+    fn from(e: ReplError) -> Self {
+        ArborError::Repl(e)
+    }
+}
+
+impl From<KeymapError> for ArborError {
+    fn from(e: KeymapError) -> Self {
+        ArborError::Config(ConfigError::Keymap(e))
+    }
+}
+
+impl From<RankerError> for ArborError {
+    fn from(e: RankerError) -> Self {
+        ArborError::Config(ConfigError::Ranker(e))
+    }
+}
+
+impl From<ThemeError> for ArborError {
+    fn from(e: ThemeError) -> Self {
+        ArborError::Config(ConfigError::Theme(e))
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum TrieError {
-    InvalidCharacter,
+    /// `position` is the byte offset of `character` within the word.
+    InvalidCharacter {
+        word: String,
+        character: char,
+        position: usize,
+    },
+    /// `position` is the byte offset within the pattern where parsing failed.
+    InvalidPattern { pattern: String, position: usize },
 }
 
 impl Error for TrieError {}
@@ -10,7 +101,403 @@ impl Error for TrieError {}
 impl Display for TrieError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TrieError::InvalidCharacter => write!(f, "Invalid ASCII character."),
+            TrieError::InvalidCharacter {
+                word,
+                character,
+                position,
+            } => write!(
+                f,
+                "Invalid character {:?} at byte {} of \"{}\".",
+                character, position, word
+            ),
+            TrieError::InvalidPattern { pattern, position } => {
+                write!(f, "Invalid pattern \"{}\" at byte {}.", pattern, position)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    CreateDir {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Open {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Read {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Write {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// `record` is the 1-based line number of the offending entry.
+    Corrupt {
+        path: PathBuf,
+        record: usize,
+        source: TrieError,
+    },
+    Dictionary {
+        path: PathBuf,
+        source: fst::Error,
+    },
+    /// A word list line whose weight isn't a whole number; `record` is its
+    /// 1-based line number.
+    InvalidWeight {
+        path: PathBuf,
+        record: usize,
+        word: String,
+    },
+    /// The file is encrypted and no key was given to unlock it.
+    Locked {
+        path: PathBuf,
+    },
+    WrongKey {
+        path: PathBuf,
+    },
+    InvalidKey {
+        path: PathBuf,
+    },
+    /// The header names a way of deriving the key this version doesn't know.
+    UnknownKdf {
+        path: PathBuf,
+        kdf: String,
+    },
+    /// `record` is the 1-based line number of the entry that failed to decrypt.
+    Tampered {
+        path: PathBuf,
+        record: usize,
+    },
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageError::CreateDir { source, .. }
+            | StorageError::Open { source, .. }
+            | StorageError::Read { source, .. }
+            | StorageError::Write { source, .. } => Some(source),
+            StorageError::Corrupt { source, .. } => Some(source),
+            StorageError::Dictionary { source, .. } => Some(source),
+            StorageError::InvalidWeight { .. }
+            | StorageError::Locked { .. }
+            | StorageError::WrongKey { .. }
+            | StorageError::InvalidKey { .. }
+            | StorageError::UnknownKdf { .. }
+            | StorageError::Tampered { .. } => None,
+        }
+    }
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::CreateDir { path, source } => write!(
+                f,
+                "Could not create directory {}: {}.",
+                path.display(),
+                source
+            ),
+            StorageError::Open { path, source } => {
+                write!(f, "Could not open {}: {}.", path.display(), source)
+            }
+            StorageError::Read { path, source } => {
+                write!(f, "Could not read {}: {}.", path.display(), source)
+            }
+            StorageError::Write { path, source } => {
+                write!(f, "Could not write to {}: {}.", path.display(), source)
+            }
+            StorageError::Corrupt {
+                path,
+                record,
+                source,
+            } => write!(
+                f,
+                "Record {} of {} is invalid: {}",
+                record,
+                path.display(),
+                source
+            ),
+            StorageError::Dictionary { path, source } => {
+                write!(f, "Invalid dictionary {}: {}.", path.display(), source)
+            }
+            StorageError::InvalidWeight { path, record, word } => write!(
+                f,
+                "Record {} of {} has an invalid weight for \"{}\".",
+                record,
+                path.display(),
+                word
+            ),
+            StorageError::Locked { path } => write!(
+                f,
+                "{} is encrypted, give its key with --key-file or --passphrase-env.",
+                path.display()
+            ),
+            StorageError::WrongKey { path } => write!(
+                f,
+                "Could not decrypt {}: wrong key or passphrase.",
+                path.display()
+            ),
+            StorageError::InvalidKey { path } => write!(
+                f,
+                "Key file {} must hold 32 bytes or 64 hex digits.",
+                path.display()
+            ),
+            StorageError::UnknownKdf { path, kdf } => write!(
+                f,
+                "{} is encrypted with an unknown key derivation `{}`.",
+                path.display(),
+                kdf
+            ),
+            StorageError::Tampered { path, record } => write!(
+                f,
+                "Record {} of {} could not be decrypted, it was changed or damaged.",
+                record,
+                path.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    HomeDirNotFound,
+    MissingThesaurus,
+    MissingBackup,
+    MissingNewKey,
+    MissingPassphrase(String),
+    UnknownCompression(String),
+    NoStemmer(String),
+    TokenizerRule {
+        line: usize,
+        rule: String,
+    },
+    /// A snippet line without a tab-separated expansion.
+    MissingExpansion {
+        path: PathBuf,
+        line: usize,
+        trigger: String,
+    },
+    /// A thesaurus line whose relation isn't known.
+    InvalidRelation {
+        path: PathBuf,
+        line: usize,
+        word: String,
+        relation: String,
+    },
+    /// A blocklist `/regex/` rule that doesn't compile.
+    InvalidRule {
+        pattern: String,
+        message: String,
+    },
+    Read {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Keymap(KeymapError),
+    Theme(ThemeError),
+    Ranker(RankerError),
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::HomeDirNotFound
+            | ConfigError::MissingThesaurus
+            | ConfigError::MissingBackup
+            | ConfigError::MissingNewKey
+            | ConfigError::MissingPassphrase(_)
+            | ConfigError::UnknownCompression(_)
+            | ConfigError::NoStemmer(_)
+            | ConfigError::TokenizerRule { .. }
+            | ConfigError::MissingExpansion { .. }
+            | ConfigError::InvalidRelation { .. }
+            | ConfigError::InvalidRule { .. } => None,
+            ConfigError::Read { source, .. } => Some(source),
+            ConfigError::Keymap(e) => Some(e),
+            ConfigError::Theme(e) => Some(e),
+            ConfigError::Ranker(e) => Some(e),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::HomeDirNotFound => write!(f, "Unable to find home directory."),
+            ConfigError::MissingThesaurus => {
+                write!(f, "Related words need a thesaurus, given with --thesaurus.")
+            }
+            ConfigError::MissingBackup => {
+                write!(f, "This command needs a backup, given with --backup.")
+            }
+            ConfigError::MissingNewKey => write!(
+                f,
+                "No new key, give one with --new-key-file or --new-passphrase-env."
+            ),
+            ConfigError::MissingPassphrase(variable) => {
+                write!(f, "No passphrase in environment variable {}.", variable)
+            }
+            ConfigError::UnknownCompression(name) => {
+                write!(f, "Unknown compression `{}`.", name.trim())
+            }
+            ConfigError::NoStemmer(language) => write!(f, "No stemmer for {}.", language),
+            ConfigError::TokenizerRule { line, rule } => {
+                write!(f, "Invalid tokenizer rule `{}` on line {}.", rule, line)
+            }
+            ConfigError::MissingExpansion {
+                path,
+                line,
+                trigger,
+            } => write!(
+                f,
+                "Missing expansion for \"{}\" on line {} of {}.",
+                trigger,
+                line,
+                path.display()
+            ),
+            ConfigError::InvalidRelation {
+                path,
+                line,
+                word,
+                relation,
+            } => write!(
+                f,
+                "Unknown relation \"{}\" for \"{}\" on line {} of {}.",
+                relation,
+                word,
+                line,
+                path.display()
+            ),
+            ConfigError::InvalidRule { pattern, message } => {
+                write!(f, "Invalid rule /{}/: {}.", pattern, message)
+            }
+            ConfigError::Read { path, source } => {
+                write!(f, "Could not read {}: {}.", path.display(), source)
+            }
+            ConfigError::Keymap(e) => write!(f, "Invalid keymap: {}", e),
+            ConfigError::Theme(e) => write!(f, "Invalid theme: {}", e),
+            ConfigError::Ranker(e) => write!(f, "Invalid ranker: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenizerError {
+    /// `offset` is the byte offset of `token` within the tokenized text.
+    Rejected {
+        token: String,
+        offset: usize,
+        reason: Rejection,
+    },
+}
+
+impl Error for TokenizerError {}
+
+impl Display for TokenizerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenizerError::Rejected {
+                token,
+                offset,
+                reason,
+            } => write!(f, "Rejected \"{}\" at byte {}: {}.", token, offset, reason),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplError {
+    Terminal(io::Error),
+}
+
+impl Error for ReplError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReplError::Terminal(source) => Some(source),
+        }
+    }
+}
+
+impl Display for ReplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplError::Terminal(source) => write!(f, "Terminal error: {}.", source),
+        }
+    }
+}
+
+impl From<io::Error> for ReplError {
+    fn from(e: io::Error) -> Self {
+        ReplError::Terminal(e)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeymapError {
+    UnknownKey(String),
+    UnknownAction(String),
+    UnknownMode(String),
+    UnknownPreset(String),
+    Syntax(usize),
+    Conflict {
+        key: String,
+        first: String,
+        second: String,
+    },
+}
+
+impl Error for KeymapError {}
+
+impl Display for KeymapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeymapError::UnknownKey(key) => write!(f, "Unknown key `{}`.", key),
+            KeymapError::UnknownAction(action) => write!(f, "Unknown action `{}`.", action),
+            KeymapError::UnknownMode(mode) => write!(f, "Unknown mode `{}`.", mode),
+            KeymapError::UnknownPreset(preset) => write!(f, "Unknown keymap preset `{}`.", preset),
+            KeymapError::Syntax(line) => write!(f, "Expected `key = action` on line {}.", line),
+            KeymapError::Conflict { key, first, second } => write!(
+                f,
+                "Key `{}` is bound to both `{}` and `{}`.",
+                key, first, second
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ThemeError {
+    UnknownTheme(String),
+}
+
+impl Error for ThemeError {}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeError::UnknownTheme(theme) => write!(f, "Unknown theme `{}`.", theme),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RankerError {
+    UnknownRanker(String),
+    InvalidWeight(String),
+}
+
+impl Error for RankerError {}
+
+impl Display for RankerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RankerError::UnknownRanker(ranker) => write!(f, "Unknown ranker `{}`.", ranker),
+            RankerError::InvalidWeight(weight) => write!(f, "Invalid weight `{}`.", weight),
         }
     }
 }
@@ -0,0 +1,368 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use super::errors::{ConfigError, KeymapError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Insert,
+    Normal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Accept,
+    Commit,
+    DeleteBackward,
+    SelectNext,
+    SelectPrevious,
+    InsertMode,
+    NormalMode,
+    Hide,
+    ToggleSubstring,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+pub struct Keymap {
+    mode: Mode,
+    bindings: HashMap<(Mode, KeyBinding), Action>,
+}
+
+impl Action {
+    fn parse(name: &str) -> Result<Self, KeymapError> {
+        Ok(match name {
+            "accept" => Action::Accept,
+            "commit" => Action::Commit,
+            "delete-backward" => Action::DeleteBackward,
+            "select-next" => Action::SelectNext,
+            "select-previous" => Action::SelectPrevious,
+            "insert-mode" => Action::InsertMode,
+            "normal-mode" => Action::NormalMode,
+            "hide" => Action::Hide,
+            "toggle-substring" => Action::ToggleSubstring,
+            "quit" => Action::Quit,
+            _ => return Err(KeymapError::UnknownAction(name.to_string())),
+        })
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Action::Accept => "accept",
+            Action::Commit => "commit",
+            Action::DeleteBackward => "delete-backward",
+            Action::SelectNext => "select-next",
+            Action::SelectPrevious => "select-previous",
+            Action::InsertMode => "insert-mode",
+            Action::NormalMode => "normal-mode",
+            Action::Hide => "hide",
+            Action::ToggleSubstring => "toggle-substring",
+            Action::Quit => "quit",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Parses bindings written as `ctrl-n`, `alt-x`, `tab`, `space` or a single character.
+    pub fn parse(key: &str) -> Result<Self, KeymapError> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = key;
+
+        loop {
+            if let Some(stripped) = rest.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+
+        let code = match rest {
+            "tab" => KeyCode::Tab,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            _ => {
+                let mut chars = rest.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(KeymapError::UnknownKey(key.to_string())),
+                }
+            }
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl Keymap {
+    pub fn emacs() -> Self {
+        let mut keymap = Self {
+            mode: Mode::Insert,
+            bindings: HashMap::new(),
+        };
+
+        for (key, action) in [
+            ("tab", Action::Accept),
+            ("enter", Action::Commit),
+            ("backspace", Action::DeleteBackward),
+            ("ctrl-h", Action::DeleteBackward),
+            ("down", Action::SelectNext),
+            ("ctrl-n", Action::SelectNext),
+            ("up", Action::SelectPrevious),
+            ("ctrl-p", Action::SelectPrevious),
+            ("ctrl-x", Action::Hide),
+            ("ctrl-s", Action::ToggleSubstring),
+            ("esc", Action::Quit),
+            ("ctrl-c", Action::Quit),
+        ] {
+            keymap.bind(Mode::Insert, key, action);
+        }
+
+        keymap
+    }
+
+    pub fn vi() -> Self {
+        let mut keymap = Self {
+            mode: Mode::Insert,
+            bindings: HashMap::new(),
+        };
+
+        for (key, action) in [
+            ("tab", Action::Accept),
+            ("enter", Action::Commit),
+            ("backspace", Action::DeleteBackward),
+            ("down", Action::SelectNext),
+            ("up", Action::SelectPrevious),
+            ("esc", Action::NormalMode),
+            ("ctrl-c", Action::Quit),
+        ] {
+            keymap.bind(Mode::Insert, key, action);
+        }
+
+        for (key, action) in [
+            ("tab", Action::Accept),
+            ("enter", Action::Commit),
+            ("x", Action::DeleteBackward),
+            ("j", Action::SelectNext),
+            ("down", Action::SelectNext),
+            ("k", Action::SelectPrevious),
+            ("up", Action::SelectPrevious),
+            ("i", Action::InsertMode),
+            ("a", Action::InsertMode),
+            ("d", Action::Hide),
+            ("/", Action::ToggleSubstring),
+            ("q", Action::Quit),
+            ("ctrl-c", Action::Quit),
+        ] {
+            keymap.bind(Mode::Normal, key, action);
+        }
+
+        keymap
+    }
+
+    pub fn preset(name: &str) -> Result<Self, KeymapError> {
+        match name {
+            "emacs" => Ok(Self::emacs()),
+            "vi" => Ok(Self::vi()),
+            _ => Err(KeymapError::UnknownPreset(name.to_string())),
+        }
+    }
+
+    /// Builds the keymap from the given preset (or the one named in the config
+    /// file, defaulting to emacs) and applies the config file's overrides.
+    pub fn build(preset: Option<&str>, config_path: Option<&Path>) -> Result<Self, ConfigError> {
+        let config = match config_path {
+            Some(path) if path.exists() => {
+                std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                })?
+            }
+            _ => String::new(),
+        };
+
+        let mut keymap = Self::preset(preset.unwrap_or("emacs")).map_err(ConfigError::Keymap)?;
+        let overrides = Self::parse_overrides(&config).map_err(ConfigError::Keymap)?;
+
+        if preset.is_none() {
+            if let Some(name) = overrides.preset {
+                keymap = Self::preset(&name).map_err(ConfigError::Keymap)?;
+            }
+        }
+
+        for (mode, key, action) in overrides.bindings {
+            keymap.bindings.insert((mode, key), action);
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn resolve(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        self.bindings
+            .get(&(self.mode, KeyBinding::new(code, modifiers)))
+            .copied()
+    }
+
+    fn bind(&mut self, mode: Mode, key: &str, action: Action) {
+        let key = KeyBinding::parse(key).expect("preset keys are valid");
+
+        self.bindings.insert((mode, key), action);
+    }
+
+    /// Parses lines of the form `[normal] ctrl-n = select-next` and an
+    /// optional `preset = vi`, rejecting keys bound twice to different actions.
+    fn parse_overrides(config: &str) -> Result<Overrides, KeymapError> {
+        let mut overrides = Overrides::default();
+        let mut seen: HashMap<(Mode, KeyBinding), Action> = HashMap::new();
+
+        for (index, line) in config.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (lhs, rhs) = line.split_once('=').ok_or(KeymapError::Syntax(index + 1))?;
+            let (lhs, rhs) = (lhs.trim(), rhs.trim());
+
+            if lhs == "preset" {
+                overrides.preset = Some(rhs.to_string());
+                continue;
+            }
+
+            let (mode, key) = match lhs.split_once(' ') {
+                Some(("[insert]", key)) => (Mode::Insert, key.trim()),
+                Some(("[normal]", key)) => (Mode::Normal, key.trim()),
+                Some((mode, _)) => return Err(KeymapError::UnknownMode(mode.to_string())),
+                None => (Mode::Insert, lhs),
+            };
+
+            let binding = KeyBinding::parse(key)?;
+            let action = Action::parse(rhs)?;
+
+            if let Some(previous) = seen.insert((mode, binding), action) {
+                if previous != action {
+                    return Err(KeymapError::Conflict {
+                        key: key.to_string(),
+                        first: previous.to_string(),
+                        second: action.to_string(),
+                    });
+                }
+            }
+
+            overrides.bindings.push((mode, binding, action));
+        }
+
+        Ok(overrides)
+    }
+}
+
+#[derive(Default)]
+struct Overrides {
+    preset: Option<String>,
+    bindings: Vec<(Mode, KeyBinding, Action)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn it_parses_key_bindings() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            KeyBinding::parse("ctrl-n")?,
+            KeyBinding::new(KeyCode::Char('n'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeyBinding::parse("space")?,
+            KeyBinding::new(KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl-nope").unwrap_err(),
+            KeymapError::UnknownKey("ctrl-nope".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_switches_vi_modes() {
+        let mut keymap = Keymap::vi();
+
+        assert_eq!(
+            keymap.resolve(KeyCode::Esc, KeyModifiers::NONE),
+            Some(Action::NormalMode)
+        );
+        assert_eq!(keymap.resolve(KeyCode::Char('j'), KeyModifiers::NONE), None);
+
+        keymap.set_mode(Mode::Normal);
+
+        assert_eq!(
+            keymap.resolve(KeyCode::Char('j'), KeyModifiers::NONE),
+            Some(Action::SelectNext)
+        );
+    }
+
+    #[test]
+    fn it_applies_overrides() -> Result<(), Box<dyn Error>> {
+        let overrides = Keymap::parse_overrides(
+            "# my bindings\npreset = vi\n[normal] ctrl-j = select-next\nctrl-q = quit\n",
+        )?;
+
+        assert_eq!(overrides.preset, Some("vi".to_string()));
+        assert_eq!(overrides.bindings.len(), 2);
+        assert_eq!(overrides.bindings[0].0, Mode::Normal);
+        assert_eq!(overrides.bindings[1].2, Action::Quit);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_detects_conflicting_bindings() {
+        let result = Keymap::parse_overrides("ctrl-n = select-next\nctrl-n = quit\n");
+
+        assert_eq!(
+            result.err().unwrap(),
+            KeymapError::Conflict {
+                key: "ctrl-n".to_string(),
+                first: "select-next".to_string(),
+                second: "quit".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crossterm::{
+    cursor, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType},
+};
+use std::io::{self, Write};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub spans: Vec<Span>,
+    pub indent: u16,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>, color: Option<Color>) -> Self {
+        Self {
+            text: text.into(),
+            color,
+        }
+    }
+}
+
+impl Line {
+    pub fn new(text: impl Into<String>, indent: u16, color: Option<Color>) -> Self {
+        Self::from_spans(vec![Span::new(text, color)], indent)
+    }
+
+    pub fn from_spans(spans: Vec<Span>, indent: u16) -> Self {
+        Self { spans, indent }
+    }
+
+    /// Shortens the line to `width` characters, cutting across spans as needed.
+    pub fn truncate(&self, width: usize) -> Self {
+        let total: usize = self.spans.iter().map(|s| s.text.chars().count()).sum();
+
+        if total <= width {
+            return self.clone();
+        }
+
+        let mut remaining = width;
+        let mut spans = Vec::new();
+
+        for span in &self.spans {
+            let length = span.text.chars().count();
+
+            if length < remaining {
+                spans.push(span.clone());
+                remaining -= length;
+                continue;
+            }
+
+            // NOTE: the last visible span carries the ellipsis
+            let mut text: String = span
+                .text
+                .chars()
+                .take(remaining.saturating_sub(1))
+                .collect();
+
+            if remaining > 0 {
+                text.push('…');
+            }
+
+            spans.push(Span::new(text, span.color));
+            break;
+        }
+
+        Self::from_spans(spans, self.indent)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Frame {
+    pub lines: Vec<Line>,
+    pub cursor: (u16, u16),
+}
+
+pub struct Renderer {
+    width: u16,
+    height: u16,
+    previous: Vec<Line>,
+    scroll_offset: usize,
+    needs_clear: bool,
+}
+
+impl Renderer {
+    pub fn new() -> io::Result<Self> {
+        let (width, height) = terminal::size()?;
+
+        Ok(Self::with_size(width, height))
+    }
+
+    pub fn with_size(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            previous: Vec::new(),
+            scroll_offset: 0,
+            needs_clear: true,
+        }
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.previous.clear();
+        self.needs_clear = true;
+    }
+
+    pub fn get_width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns the slice of a `total`-long list that fits below the `reserved`
+    /// header rows, scrolled just enough to keep `selected` visible.
+    pub fn visible_range(
+        &mut self,
+        selected: usize,
+        total: usize,
+        reserved: usize,
+    ) -> Range<usize> {
+        let rows = (self.height as usize).saturating_sub(reserved).max(1);
+
+        if selected < self.scroll_offset {
+            self.scroll_offset = selected;
+        } else if selected >= self.scroll_offset + rows {
+            self.scroll_offset = selected + 1 - rows;
+        }
+
+        // NOTE: the list may have shrunk since the last frame
+        self.scroll_offset = self.scroll_offset.min(total.saturating_sub(rows));
+
+        self.scroll_offset..total.min(self.scroll_offset + rows)
+    }
+
+    pub fn draw<W: Write>(&mut self, out: &mut W, frame: &Frame) -> io::Result<()> {
+        if self.needs_clear {
+            queue!(out, Clear(ClearType::All))?;
+            self.needs_clear = false;
+        }
+
+        let lines: Vec<Line> = frame
+            .lines
+            .iter()
+            .take(self.height as usize)
+            .map(|line| line.truncate(self.width.saturating_sub(line.indent) as usize))
+            .collect();
+
+        for row in changed_rows(&self.previous, &lines) {
+            queue!(
+                out,
+                cursor::MoveTo(0, row as u16),
+                Clear(ClearType::CurrentLine)
+            )?;
+
+            if let Some(line) = lines.get(row) {
+                queue!(out, cursor::MoveTo(line.indent, row as u16))?;
+
+                for span in &line.spans {
+                    match span.color {
+                        Some(color) => queue!(
+                            out,
+                            SetForegroundColor(color),
+                            Print(&span.text),
+                            ResetColor
+                        )?,
+                        None => queue!(out, Print(&span.text))?,
+                    }
+                }
+            }
+        }
+
+        queue!(out, cursor::MoveTo(frame.cursor.0, frame.cursor.1))?;
+        out.flush()?;
+
+        self.previous = lines;
+
+        Ok(())
+    }
+}
+
+/// Rows that differ between two frames, including rows that only exist in the
+/// previous one and therefore have to be blanked out.
+pub fn changed_rows(previous: &[Line], next: &[Line]) -> Vec<usize> {
+    (0..previous.len().max(next.len()))
+        .filter(|&i| previous.get(i) != next.get(i))
+        .collect()
+}
+
+/// Shortens `text` to `width` characters, marking the cut with an ellipsis.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut truncated: String = text.chars().take(width - 1).collect();
+    truncated.push('…');
+
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_redraws_only_changed_rows() {
+        let previous = vec![
+            Line::new("> he", 0, None),
+            Line::new("hello", 2, Some(Color::Green)),
+            Line::new("help", 2, Some(Color::DarkGrey)),
+        ];
+        let next = vec![
+            Line::new("> he", 0, None),
+            Line::new("hello", 2, Some(Color::DarkGrey)),
+        ];
+
+        assert_eq!(changed_rows(&previous, &next), vec![1, 2]);
+        assert!(changed_rows(&next, &next).is_empty());
+    }
+
+    #[test]
+    fn it_scrolls_to_selected_suggestion() {
+        let mut renderer = Renderer::with_size(80, 4);
+
+        assert_eq!(renderer.visible_range(0, 10, 1), 0..3);
+        assert_eq!(renderer.visible_range(5, 10, 1), 3..6);
+        assert_eq!(renderer.visible_range(4, 10, 1), 3..6);
+        assert_eq!(renderer.visible_range(1, 10, 1), 1..4);
+        assert_eq!(renderer.visible_range(1, 2, 1), 0..2);
+        assert_eq!(renderer.visible_range(3, 10, 2), 2..4);
+    }
+
+    #[test]
+    fn it_truncates_wide_entries() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("helicopter", 5), "heli…");
+        assert_eq!(truncate("hello", 0), "");
+
+        let line = Line::from_spans(
+            vec![
+                Span::new("hel", Some(Color::Yellow)),
+                Span::new("icopter", None),
+            ],
+            2,
+        );
+
+        assert_eq!(
+            line.truncate(3).spans,
+            vec![Span::new("he…", Some(Color::Yellow))]
+        );
+        assert_eq!(
+            line.truncate(5).spans,
+            vec![Span::new("hel", Some(Color::Yellow)), Span::new("i…", None)]
+        );
+    }
+}
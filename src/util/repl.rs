@@ -1,205 +1,743 @@
 use crossterm::{
-    cursor,
-    event::{self, Event, KeyCode, KeyModifiers},
-    execute,
-    style::{Color, Print, ResetColor, SetForegroundColor},
-    terminal::{self, Clear, ClearType},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyModifiers},
+    execute, terminal,
 };
+use std::io;
+use std::ops::ControlFlow;
+use std::path::Path;
 use std::time::Duration;
-use std::{
-    error::Error,
-    io::{self, Write},
+
+use crate::common::{
+    app_builder::Arbor,
+    autocomplete::{Entry, Suggestion},
+    dictionary::Layer,
+    filter::FilterStats,
+    pattern::Pattern,
+    snippets,
+    thesaurus::Relation,
+    tokenizer::{Rejection, TokenReport},
 };
 
-use crate::common::app_builder::Arbor;
+use super::errors::{ArborError, ReplError, TrieError};
+
+use super::keymap::{Action, Mode};
+use super::renderer::{Frame, Line, Renderer};
+
+/// What the highlighted suggestion stands for.
+enum Selected {
+    Expansion { text: String, snippet: bool },
+    Word(String),
+}
+
+/// A snippet trigger or shortcode listed above the word suggestions, and
+/// the text it expands to.
+struct Expansion<'a> {
+    trigger: String,
+    text: &'a str,
+    snippet: bool,
+}
 
 pub struct Repl {
     arbor: Arbor,
     input: String,
     input_section: usize,
     selected_suggestion: usize,
+    status: Option<String>,
+    /// Whether words are matched by any fragment rather than by prefix.
+    substring: bool,
 }
 
 impl Repl {
-    pub async fn new() -> Result<Self, Box<dyn Error>> {
-        Ok(Self {
-            arbor: Arbor::build().await?,
+    pub async fn new() -> Result<Self, ArborError> {
+        Self::build(Arbor::build().await?).await
+    }
+
+    pub fn with_arbor(arbor: Arbor) -> Self {
+        Self {
+            arbor,
             input: "".to_string(),
             input_section: 0,
             selected_suggestion: 0,
-        })
+            status: None,
+            substring: false,
+        }
+    }
+
+    /// A REPL over `arbor` that starts with the learned words of its backup.
+    pub async fn build(mut arbor: Arbor) -> Result<Self, ArborError> {
+        arbor.autocomplete.load_backup().await?;
+
+        Ok(Self::with_arbor(arbor))
     }
 
-    pub async fn run(&mut self) -> Result<(), Box<dyn Error>> {
+    pub async fn run(&mut self) -> Result<(), ArborError> {
         let mut stdout = io::stdout();
 
-        terminal::enable_raw_mode()?;
-        execute!(stdout, terminal::EnterAlternateScreen)?;
+        terminal::enable_raw_mode().map_err(ReplError::Terminal)?;
+        execute!(stdout, terminal::EnterAlternateScreen, EnableBracketedPaste)
+            .map_err(ReplError::Terminal)?;
+
+        let result = self.event_loop(&mut stdout).await;
+
+        // NOTE: restore the terminal before any error gets printed
+        execute!(
+            stdout,
+            DisableBracketedPaste,
+            terminal::LeaveAlternateScreen
+        )
+        .map_err(ReplError::Terminal)?;
+        terminal::disable_raw_mode().map_err(ReplError::Terminal)?;
+
+        result
+    }
+
+    async fn event_loop(&mut self, stdout: &mut io::Stdout) -> Result<(), ArborError> {
+        let mut renderer = Renderer::new().map_err(ReplError::Terminal)?;
+        let mut dirty = true;
 
         loop {
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(event) = event::read()? {
-                    match event.code {
-                        KeyCode::Char(' ') => {
-                            if self.input.is_empty() {
-                                continue;
-                            }
-
-                            self.input.push(' ');
-                            self.input_section += 1;
-
-                            if self
-                                .input
-                                .chars()
-                                .nth(self.input.len().saturating_sub(2))
-                                .unwrap()
-                                == ' '
-                            {
-                                self.input.pop();
-                                self.input_section -= 1;
-                            }
-                        }
-                        KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                            break
-                        }
-                        KeyCode::Char(c) => {
-                            self.input.push(c);
-                            self.selected_suggestion = 0;
-                        }
-                        KeyCode::Backspace => {
-                            if self.input.is_empty() {
-                                continue;
-                            }
-
-                            let curr = self
-                                .input
-                                .chars()
-                                .nth(self.input.len().saturating_sub(1))
-                                .unwrap();
-
-                            if curr == ' ' {
-                                self.input_section -= 1;
-                            }
-
-                            self.input.pop();
-                            self.selected_suggestion = 0;
-                        }
-                        KeyCode::Enter => {
-                            let words = self.input.split(' ').collect::<Vec<&str>>();
-
-                            for word in words {
-                                // word length must be bigger than 1 character
-                                if word.len() < 2 {
-                                    continue;
-                                }
-
-                                self.arbor
-                                    .autocomplete
-                                    .insert_word(word.to_string())
-                                    .await?;
-                            }
-
-                            self.input = "".to_string();
-                            self.selected_suggestion = 0;
-                            self.input_section = 0;
-                        }
-                        KeyCode::Up => {
-                            if self.selected_suggestion > 0 {
-                                self.selected_suggestion -= 1;
-                            }
-                        }
-                        KeyCode::Down => {
-                            self.selected_suggestion += 1;
-                        }
-                        KeyCode::Tab => {
-                            if self.input.is_empty() {
-                                continue;
-                            }
-
-                            if let Some(suggestion) = self
-                                .arbor
-                                .autocomplete
-                                .suggest_word(
-                                    self.input
-                                        .split(' ')
-                                        .collect::<Vec<&str>>()
-                                        .get(self.input_section)
-                                        .unwrap(),
-                                )
-                                .await?
-                                .get(self.selected_suggestion)
-                            {
-                                let mut words = self.input.split(' ').collect::<Vec<&str>>();
-
-                                words[self.input_section] = suggestion.as_str();
-
-                                self.input = words.join(" ");
-                            }
-
-                            self.selected_suggestion = 0;
-
-                            self.input.push(' ');
-                            self.input_section += 1;
+            if event::poll(Duration::from_millis(100)).map_err(ReplError::Terminal)? {
+                match event::read().map_err(ReplError::Terminal)? {
+                    Event::Key(event) => {
+                        self.status = None;
+
+                        if self
+                            .handle_key(event.code, event.modifiers)
+                            .await?
+                            .is_break()
+                        {
+                            break;
                         }
-                        KeyCode::Esc => break,
-                        _ => panic!("Unknown keystroke!"),
                     }
+                    Event::Paste(text) => self.handle_paste(&text).await?,
+                    Event::Resize(width, height) => renderer.resize(width, height),
+                    _ => continue,
                 }
+
+                dirty = true;
+            }
+
+            // NOTE: nothing changed since the last frame, skip redrawing
+            if !dirty {
+                continue;
             }
 
-            let suggestions = self
-                .arbor
-                .autocomplete
-                .suggest_word(
-                    self.input
-                        .split(' ')
-                        .collect::<Vec<&str>>()
-                        .get(self.input_section)
-                        .unwrap(),
-                )
-                .await?;
+            let (previous, prefix) = self.current_word();
+            let expansions = expansions(&self.arbor, &prefix, self.substring);
+            let suggestions =
+                suggestions(&self.arbor, previous.as_deref(), &prefix, self.substring).await?;
+            let heads = stem_heads(&self.arbor, &suggestions);
+            let total = expansions.len() + suggestions.len();
+            let related = related_panel(&self.arbor, &prefix, self.substring);
 
             // NOTE: this is to prevent selection overflow
-            let max_index = suggestions.len().saturating_sub(1);
+            let max_index = total.saturating_sub(1);
             if self.selected_suggestion > max_index {
                 self.selected_suggestion = max_index;
             }
 
-            execute!(stdout, Clear(ClearType::All))?;
+            let theme = &self.arbor.theme;
+            let prompt = match self.arbor.keymap.get_mode() {
+                Mode::Insert => &theme.prompt,
+                Mode::Normal => &theme.normal_prompt,
+            };
 
-            execute!(
-                stdout,
-                cursor::MoveTo(0, 0),
-                Print(format!("> {}", self.input))
-            )?;
+            // NOTE: expanded snippets may span lines, the prompt only has one
+            let input = format!("{}{}", prompt, self.input.replace('\n', "↵"));
+            let mut frame = Frame {
+                cursor: (input.chars().count() as u16, 0),
+                lines: vec![Line::new(input, 0, None)],
+            };
 
-            for (i, suggestion) in suggestions.iter().enumerate() {
-                if i == self.selected_suggestion {
-                    execute!(
-                        stdout,
-                        cursor::MoveTo(2, (i + 1) as u16),
-                        SetForegroundColor(Color::Green),
-                        Print(suggestion),
-                        ResetColor
-                    )?;
-                } else {
-                    execute!(
-                        stdout,
-                        cursor::MoveTo(2, (i + 1) as u16),
-                        SetForegroundColor(Color::DarkGrey),
-                        Print(suggestion),
-                        ResetColor
-                    )?;
+            if let Some(status) = &self.status {
+                frame.lines.push(Line::new(status.as_str(), 0, theme.score));
+            }
+
+            // NOTE: the related words panel goes below the list, keep room for it
+            let reserved = frame.lines.len() + related.len();
+
+            for i in renderer.visible_range(self.selected_suggestion, total, reserved) {
+                if let Some(expansion) = expansions.get(i) {
+                    let preview = match expansion.text.split_once('\n') {
+                        Some((first, _)) => format!("{}…", first),
+                        None => expansion.text.to_string(),
+                    };
+
+                    frame.lines.push(theme.suggestion_line(
+                        &expansion.trigger,
+                        &prefix,
+                        0,
+                        Some(&preview),
+                        expansion.snippet.then_some("snippet"),
+                        i == self.selected_suggestion,
+                    ));
+                    continue;
+                }
+
+                let suggestion = &suggestions[i - expansions.len()];
+                // NOTE: learned words are the common case, only tag dictionary words
+                let layer = match suggestion.layer {
+                    Layer::Personal => None,
+                    layer => Some(layer.to_string()),
+                };
+                let tag = match (layer, heads[i - expansions.len()]) {
+                    (Some(layer), Some(head)) => Some(format!("{}, ~{}", layer, head)),
+                    (None, Some(head)) => Some(format!("~{}", head)),
+                    (layer, None) => layer,
+                };
+
+                frame.lines.push(theme.suggestion_line(
+                    &suggestion.word,
+                    &prefix,
+                    suggestion.score,
+                    suggestion.value.description.as_deref(),
+                    tag.as_deref(),
+                    i == self.selected_suggestion,
+                ));
+            }
+
+            for (label, words) in &related {
+                let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+                frame.lines.push(theme.related_line(label, &words));
+            }
+
+            renderer.draw(stdout, &frame).map_err(ReplError::Terminal)?;
+            dirty = false;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_key(
+        &mut self,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> Result<ControlFlow<()>, ArborError> {
+        let action = match self.arbor.keymap.resolve(code, modifiers) {
+            Some(action) => action,
+            None => {
+                // NOTE: unbound characters are typed only in insert mode
+                if let (Mode::Insert, KeyCode::Char(c)) = (self.arbor.keymap.get_mode(), code) {
+                    if modifiers.difference(KeyModifiers::SHIFT).is_empty() {
+                        self.insert_char(c);
+                    }
+                }
+
+                return Ok(ControlFlow::Continue(()));
+            }
+        };
+
+        match action {
+            Action::Quit => return Ok(ControlFlow::Break(())),
+            Action::InsertMode => self.arbor.keymap.set_mode(Mode::Insert),
+            Action::NormalMode => self.arbor.keymap.set_mode(Mode::Normal),
+            Action::DeleteBackward => {
+                if self.input.is_empty() {
+                    return Ok(ControlFlow::Continue(()));
                 }
+
+                if self.input.ends_with(' ') {
+                    self.input_section -= 1;
+                }
+
+                self.input.pop();
+                self.selected_suggestion = 0;
+            }
+            Action::Commit if self.input.starts_with(':') => {
+                let command = std::mem::take(&mut self.input);
+
+                self.status = Some(self.run_command(&command).await);
+                self.selected_suggestion = 0;
+                self.input_section = 0;
+            }
+            Action::Commit => {
+                let report = self.arbor.autocomplete.learn_text(&self.input).await?;
+
+                self.status = rejection_status(&report);
+                self.input = "".to_string();
+                self.selected_suggestion = 0;
+                self.input_section = 0;
+            }
+            Action::SelectPrevious => {
+                if self.selected_suggestion > 0 {
+                    self.selected_suggestion -= 1;
+                }
+            }
+            Action::SelectNext => {
+                self.selected_suggestion += 1;
+            }
+            Action::ToggleSubstring => {
+                if self.arbor.autocomplete.get_infix_index().is_none() {
+                    self.status = Some("substring search needs --infix-index".to_string());
+                    return Ok(ControlFlow::Continue(()));
+                }
+
+                self.substring = !self.substring;
+                self.selected_suggestion = 0;
+                self.status = Some(format!(
+                    "substring search {}",
+                    if self.substring { "on" } else { "off" }
+                ));
             }
+            Action::Hide => match self.selected().await? {
+                Some(Selected::Word(word)) => {
+                    self.status = Some(format!("hidden: {}", word));
+                    self.arbor.autocomplete.hide_word(word).await?;
+                }
+                Some(Selected::Expansion { snippet: true, .. }) => {
+                    self.status = Some("remove snippets with :unsnippet".to_string());
+                }
+                Some(Selected::Expansion { snippet: false, .. }) | None => {}
+            },
+            Action::Accept => {
+                if self.input.is_empty() {
+                    return Ok(ControlFlow::Continue(()));
+                }
+
+                if let Some(Selected::Word(text) | Selected::Expansion { text, .. }) =
+                    self.selected().await?
+                {
+                    let mut words = self.input.split(' ').collect::<Vec<&str>>();
+
+                    words[self.input_section] = text.as_str();
+
+                    self.input = words.join(" ");
+                }
+
+                self.selected_suggestion = 0;
+
+                self.input.push(' ');
+                // NOTE: an expansion may have brought spaces of its own
+                self.input_section = self.input.matches(' ').count();
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
 
-            execute!(stdout, cursor::MoveTo(2 + self.input.len() as u16, 0))?;
+    /// Inserts pasted text into the prompt, and learns its words too when
+    /// `--learn-paste` is set.
+    async fn handle_paste(&mut self, text: &str) -> Result<(), ArborError> {
+        // NOTE: newlines must not act as Enter, they only separate words
+        for c in text.chars() {
+            self.insert_char(if c.is_whitespace() { ' ' } else { c });
+        }
+
+        self.selected_suggestion = 0;
 
-            stdout.flush()?;
+        if self.arbor.learn_paste {
+            let report = self.arbor.autocomplete.learn_text(text).await?;
+
+            self.status = rejection_status(&report);
         }
 
-        execute!(stdout, terminal::LeaveAlternateScreen)?;
-        terminal::disable_raw_mode()?;
+        Ok(())
+    }
+
+    /// The word before the one being typed, if any, and the one being typed.
+    fn current_word(&self) -> (Option<String>, String) {
+        let words: Vec<&str> = self.input.split(' ').collect();
+        let previous = self
+            .input_section
+            .checked_sub(1)
+            .and_then(|i| words.get(i))
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_string());
+        let prefix = words.get(self.input_section).copied().unwrap_or_default();
+
+        (previous, prefix.to_string())
+    }
+
+    async fn selected(&self) -> Result<Option<Selected>, ArborError> {
+        let (previous, prefix) = self.current_word();
+        let expansions = expansions(&self.arbor, &prefix, self.substring);
+
+        if let Some(expansion) = expansions.get(self.selected_suggestion) {
+            return Ok(Some(Selected::Expansion {
+                text: expansion.text.to_string(),
+                snippet: expansion.snippet,
+            }));
+        }
+
+        Ok(
+            suggestions(&self.arbor, previous.as_deref(), &prefix, self.substring)
+                .await?
+                .into_iter()
+                .nth(self.selected_suggestion - expansions.len())
+                .map(|suggestion| Selected::Word(suggestion.word)),
+        )
+    }
+
+    /// Runs a `:command` typed at the prompt and describes the outcome.
+    async fn run_command(&mut self, input: &str) -> String {
+        let mut parts = input.splitn(3, ' ');
+        let command = parts.next().unwrap_or_default();
+        let argument = parts.next().unwrap_or_default();
+        let rest = parts.next().unwrap_or_default();
+        let snippets = &mut self.arbor.snippets;
+
+        let result = match command {
+            ":snippet" if !argument.is_empty() && !rest.is_empty() => snippets
+                .define(argument, snippets::unescape(rest))
+                .await
+                .map(|_| format!("snippet: {}", argument)),
+            ":unsnippet" if !argument.is_empty() => {
+                snippets.remove(argument).await.map(|removed| {
+                    if removed {
+                        format!("removed snippet: {}", argument)
+                    } else {
+                        format!("no snippet: {}", argument)
+                    }
+                })
+            }
+            ":snippets" => {
+                let triggers = snippets
+                    .suggest("")
+                    .into_iter()
+                    .map(|(trigger, _)| trigger)
+                    .collect::<Vec<String>>();
+
+                Ok(if triggers.is_empty() {
+                    "no snippets".to_string()
+                } else {
+                    format!("snippets: {}", triggers.join(", "))
+                })
+            }
+            ":import" if !argument.is_empty() => {
+                let path = [argument, rest].join(" ");
+
+                snippets
+                    .import(Path::new(path.trim()))
+                    .await
+                    .map(|count| format!("imported {} snippets", count))
+            }
+            ":filtered" => Ok(filter_status(self.arbor.autocomplete.get_filter_stats())),
+            ":incognito" => {
+                let autocomplete = &mut self.arbor.autocomplete;
+                autocomplete.set_incognito(!autocomplete.is_incognito());
+
+                Ok(if autocomplete.is_incognito() {
+                    "incognito: new words are not saved".to_string()
+                } else {
+                    "incognito off".to_string()
+                })
+            }
+            ":purge" if !argument.is_empty() => {
+                self.arbor
+                    .autocomplete
+                    .purge(argument)
+                    .await
+                    .map(|purged| match purged.len() {
+                        0 => format!("nothing matches {}", argument),
+                        count => format!("purged {} words: {}", count, purged.join(", ")),
+                    })
+            }
+            ":snippet" => Ok("usage: :snippet TRIGGER EXPANSION".to_string()),
+            ":unsnippet" => Ok("usage: :unsnippet TRIGGER".to_string()),
+            ":import" => Ok("usage: :import FILE".to_string()),
+            ":purge" => Ok("usage: :purge PATTERN".to_string()),
+            _ => Ok(format!("unknown command: {}", command)),
+        };
+
+        result.unwrap_or_else(|e| e.to_string())
+    }
+
+    fn insert_char(&mut self, c: char) {
+        if c != ' ' {
+            self.input.push(c);
+            self.selected_suggestion = 0;
+
+            return;
+        }
+
+        // NOTE: words are separated by a single space
+        if self.input.is_empty() || self.input.ends_with(' ') {
+            return;
+        }
+
+        self.input.push(' ');
+        self.input_section += 1;
+    }
+}
+
+/// Words related to the one being typed, one row per relation, e.g.
+/// `("synonyms", ["glad", "cheerful"])`.
+fn related_panel(arbor: &Arbor, prefix: &str, substring: bool) -> Vec<(&'static str, Vec<String>)> {
+    if prefix.is_empty() || substring || Pattern::is_pattern(prefix) || is_shortcode(arbor, prefix)
+    {
+        return Vec::new();
+    }
+
+    let mut rows: Vec<(&'static str, Vec<String>)> = Vec::new();
+
+    // NOTE: related words come grouped by relation
+    for related in arbor.autocomplete.related_words(prefix) {
+        let label = match related.relation {
+            Relation::Synonym => "synonyms",
+            Relation::Antonym => "antonyms",
+            Relation::Related => "related",
+        };
+
+        match rows.last_mut() {
+            Some((last, words)) if *last == label => words.push(related.word),
+            _ => rows.push((label, Vec::from([related.word]))),
+        }
+    }
+
+    rows
+}
+
+/// Whether the word being typed starts with the sigil of an enabled
+/// shortcode set, e.g. `:smi` or `\alp`.
+fn is_shortcode(arbor: &Arbor, prefix: &str) -> bool {
+    arbor
+        .shortcodes
+        .as_ref()
+        .is_some_and(|shortcodes| shortcodes.is_shortcode(prefix))
+}
+
+/// Shortcodes when the word being typed starts with a sigil, and snippets
+/// whose trigger starts with it otherwise, listed above the word
+/// suggestions. Wildcards and substring search skip both.
+fn expansions<'a>(arbor: &'a Arbor, prefix: &str, substring: bool) -> Vec<Expansion<'a>> {
+    if prefix.is_empty() || substring || Pattern::is_pattern(prefix) {
+        return Vec::new();
+    }
+
+    if let (true, Some(shortcodes)) = (is_shortcode(arbor, prefix), &arbor.shortcodes) {
+        let limit = arbor.autocomplete.get_app_data().get_max_suggestion() as usize;
+
+        return shortcodes
+            .suggest(prefix, limit)
+            .into_iter()
+            .map(|(trigger, text)| Expansion {
+                trigger,
+                text,
+                snippet: false,
+            })
+            .collect();
+    }
+
+    arbor
+        .snippets
+        .suggest(prefix)
+        .into_iter()
+        .map(|(trigger, text)| Expansion {
+            trigger,
+            text,
+            snippet: true,
+        })
+        .collect()
+}
+
+/// Suggestions for the word being typed: words containing it in substring
+/// mode, its matches when it contains wildcards such as `h?ll*`, and words
+/// starting with it otherwise. Inflections of one stem are listed together.
+async fn suggestions<'a>(
+    arbor: &'a Arbor,
+    previous: Option<&str>,
+    prefix: &str,
+    substring: bool,
+) -> Result<Vec<Suggestion<'a, Entry>>, ArborError> {
+    let autocomplete = &arbor.autocomplete;
+
+    // NOTE: a shortcode is never a word, don't look for sound-alikes of it
+    if is_shortcode(arbor, prefix) {
+        return Ok(Vec::new());
+    }
+
+    if substring && !prefix.is_empty() {
+        let suggestions = autocomplete.suggest_containing(previous, prefix).await?;
+
+        return Ok(autocomplete.group_by_stem(suggestions));
+    }
+
+    if !Pattern::is_pattern(prefix) {
+        let suggestions = autocomplete.suggest_phonetic(previous, prefix).await?;
+
+        return Ok(autocomplete.group_by_stem(suggestions));
+    }
+
+    match autocomplete.match_pattern(prefix).await {
+        Ok(matches) => Ok(matches.suggestions),
+        // NOTE: a pattern is usually malformed only because it is half typed
+        Err(ArborError::Trie(TrieError::InvalidPattern { .. })) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// For each suggestion, the first word of its stem group when it isn't that
+/// word itself, e.g. `running` for `runs`, so inflections can be tagged.
+fn stem_heads<'a>(arbor: &Arbor, suggestions: &'a [Suggestion<'_, Entry>]) -> Vec<Option<&'a str>> {
+    let autocomplete = &arbor.autocomplete;
+    let mut heads = Vec::with_capacity(suggestions.len());
+    let mut group: Option<(String, &str)> = None;
+
+    for suggestion in suggestions {
+        let Some(stem) = autocomplete.stem(&suggestion.word) else {
+            heads.push(None);
+            continue;
+        };
+
+        match &group {
+            Some((known, head)) if *known == stem => heads.push(Some(*head)),
+            _ => {
+                group = Some((stem, suggestion.word.as_str()));
+                heads.push(None);
+            }
+        }
+    }
+
+    heads
+}
+
+/// How many inputs were filtered, e.g. `filtered 3: 2 stop words, 1 token`.
+fn filter_status(stats: &FilterStats) -> String {
+    let mut counts = Vec::new();
+
+    if stats.stop_words > 0 {
+        counts.push(format!("{} stop words", stats.stop_words));
+    }
+
+    if stats.blocked > 0 {
+        counts.push(format!("{} blocked", stats.blocked));
+    }
+
+    if stats.secrets > 0 {
+        counts.push(format!("{} secrets", stats.secrets));
+    }
+
+    for (rule, count) in &stats.rules {
+        counts.push(format!("{} {}", count, rule));
+    }
+
+    if counts.is_empty() {
+        return "nothing filtered".to_string();
+    }
+
+    format!("filtered {}: {}", stats.total(), counts.join(", "))
+}
+
+/// Keeps the first characters of a secret so it can be recognized, e.g.
+/// `sk-A…`.
+fn redact(secret: &str) -> String {
+    format!("{}…", secret.chars().take(4).collect::<String>())
+}
+
+fn rejection_status(report: &TokenReport) -> Option<String> {
+    if report.rejected.is_empty() {
+        return None;
+    }
+
+    let skipped = report
+        .rejected
+        .iter()
+        .map(|rejected| match rejected.reason {
+            // NOTE: don't echo a secret back to the screen
+            Rejection::Secret => format!("{} ({})", redact(&rejected.token.text), rejected.reason),
+            _ => format!("{} ({})", rejected.token.text, rejected.reason),
+        })
+        .collect::<Vec<String>>();
+
+    Some(format!("skipped: {}", skipped.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::common::app_builder::Args;
+
+    use super::*;
+
+    async fn init_repl(args: &[&str]) -> Repl {
+        let args = Args::try_parse_from(args).unwrap();
+
+        Repl::with_arbor(Arbor::build_with_args(args).await.unwrap())
+    }
+
+    fn type_text(repl: &mut Repl, text: &str) {
+        for c in text.chars() {
+            repl.insert_char(c);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_types_after_an_accepted_shortcode() -> Result<(), ArborError> {
+        let mut repl = init_repl(&["arbor", "--shortcodes"]).await;
+
+        type_text(&mut repl, ":smile");
+        assert_eq!(
+            repl.handle_key(KeyCode::Tab, KeyModifiers::NONE).await?,
+            ControlFlow::Continue(())
+        );
+
+        let glyph = repl.input.trim_end().to_string();
+
+        assert!(!glyph.is_ascii());
+
+        type_text(&mut repl, "hi ");
+
+        assert_eq!(repl.input, format!("{} hi ", glyph));
+        assert_eq!(repl.input_section, 2);
+
+        assert_eq!(
+            repl.handle_key(KeyCode::Backspace, KeyModifiers::NONE)
+                .await?,
+            ControlFlow::Continue(())
+        );
+
+        assert_eq!(repl.input, format!("{} hi", glyph));
+        assert_eq!(repl.input_section, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_loads_the_backup_on_start() -> Result<(), ArborError> {
+        let path = std::env::temp_dir().join(format!("arbor-repl-backup-{}", std::process::id()));
+        std::fs::write(&path, "habitual\t1700000000\n").unwrap();
+
+        let args = Args::try_parse_from(["arbor", "--backup", "--output", path.to_str().unwrap()])
+            .unwrap();
+        let repl = Repl::build(Arbor::build_with_args(args).await?).await?;
+
+        assert_eq!(
+            repl.arbor.autocomplete.suggest_word("habitu").await?,
+            ["habitual"]
+        );
+
+        std::fs::remove_file(path).unwrap();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn it_inserts_and_learns_pasted_text() -> Result<(), ArborError> {
+        let mut repl = init_repl(&["arbor", "--learn-paste"]).await;
+
+        repl.handle_paste("crème brûlée\nhabitual  café\n").await?;
+
+        assert_eq!(repl.input, "crème brûlée habitual café ");
+        assert_eq!(repl.input_section, 4);
+        assert!(repl.status.as_deref().unwrap().contains("brûlée"));
+        assert_eq!(
+            repl.arbor.autocomplete.suggest_word("habitu").await?,
+            ["habitual"]
+        );
+
+        let mut repl = init_repl(&["arbor"]).await;
+
+        repl.handle_paste("über\nnaïveté").await?;
+
+        assert_eq!(repl.input, "über naïveté");
+        assert!(repl
+            .arbor
+            .autocomplete
+            .suggest_word("übe")
+            .await?
+            .is_empty());
 
         Ok(())
     }
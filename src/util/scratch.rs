@@ -0,0 +1,47 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::common::dictionary::Dictionary;
+
+/// A scratch directory for test files, unique to the test process and
+/// removed once dropped.
+pub(crate) struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    pub(crate) fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "arbor-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        Self { dir }
+    }
+
+    pub(crate) fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Compiles `word<TAB>weight` lines into a dictionary and opens it.
+    pub(crate) fn dictionary(&self, name: &str, words: &str) -> Result<Dictionary, Box<dyn Error>> {
+        let word_list = self.path(name);
+        let output = self.path(&format!("{}.fst", name));
+
+        std::fs::write(&word_list, words)?;
+        Dictionary::compile(&word_list, &output)?;
+
+        Ok(Dictionary::open(&output)?)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
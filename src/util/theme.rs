@@ -0,0 +1,212 @@
+use crossterm::style::Color;
+
+use super::errors::ThemeError;
+use super::renderer::{Line, Span};
+
+pub struct Theme {
+    pub prompt: String,
+    pub normal_prompt: String,
+    pub selected: Option<Color>,
+    pub suggestion: Option<Color>,
+    pub highlight: Option<Color>,
+    pub score: Option<Color>,
+    pub description: Option<Color>,
+    pub selection_marker: String,
+    pub show_score: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            prompt: "> ".to_string(),
+            normal_prompt: ": ".to_string(),
+            selected: Some(Color::Green),
+            suggestion: Some(Color::DarkGrey),
+            highlight: None,
+            score: Some(Color::DarkGrey),
+            description: Some(Color::DarkCyan),
+            selection_marker: "  ".to_string(),
+            show_score: false,
+        }
+    }
+}
+
+impl Theme {
+    pub fn no_color() -> Self {
+        Self {
+            selected: None,
+            suggestion: None,
+            highlight: None,
+            score: None,
+            description: None,
+            // NOTE: without colors the marker is the only selection cue
+            selection_marker: "* ".to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            selected: Some(Color::White),
+            suggestion: Some(Color::Grey),
+            highlight: Some(Color::Yellow),
+            score: Some(Color::Cyan),
+            description: Some(Color::Magenta),
+            selection_marker: "> ".to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn preset(name: &str) -> Result<Self, ThemeError> {
+        match name {
+            "default" => Ok(Self::default()),
+            "no-color" => Ok(Self::no_color()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            _ => Err(ThemeError::UnknownTheme(name.to_string())),
+        }
+    }
+
+    /// Resolves the theme by name, falling back to no-color when `NO_COLOR`
+    /// is set and to the default theme otherwise.
+    pub fn build(
+        name: Option<&str>,
+        prompt: Option<String>,
+        normal_prompt: Option<String>,
+        show_score: bool,
+    ) -> Result<Self, ThemeError> {
+        let mut theme = match name {
+            Some(name) => Self::preset(name)?,
+            None if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => Self::no_color(),
+            None => Self::default(),
+        };
+
+        if let Some(prompt) = prompt {
+            theme.prompt = prompt;
+        }
+
+        if let Some(normal_prompt) = normal_prompt {
+            theme.normal_prompt = normal_prompt;
+        }
+
+        theme.show_score = show_score;
+
+        Ok(theme)
+    }
+
+    pub fn suggestion_line(
+        &self,
+        word: &str,
+        prefix: &str,
+        score: u32,
+        description: Option<&str>,
+        layer: Option<&str>,
+        selected: bool,
+    ) -> Line {
+        let color = if selected {
+            self.selected
+        } else {
+            self.suggestion
+        };
+        let marker = if selected {
+            self.selection_marker.clone()
+        } else {
+            " ".repeat(self.selection_marker.chars().count())
+        };
+
+        let mut spans = vec![Span::new(marker, color)];
+
+        match (self.highlight, word.strip_prefix(prefix)) {
+            (Some(highlight), Some(rest)) if !prefix.is_empty() => {
+                spans.push(Span::new(prefix, Some(highlight)));
+                spans.push(Span::new(rest, color));
+            }
+            _ => spans.push(Span::new(word, color)),
+        }
+
+        if self.show_score {
+            spans.push(Span::new(format!(" ({})", score), self.score));
+        }
+
+        if let Some(layer) = layer {
+            spans.push(Span::new(format!(" [{}]", layer), self.score));
+        }
+
+        if let Some(description) = description {
+            spans.push(Span::new(format!("  {}", description), self.description));
+        }
+
+        Line::from_spans(spans, 0)
+    }
+
+    /// A row of the related-words panel, e.g. `synonyms: glad, cheerful`.
+    pub fn related_line(&self, label: &str, words: &[&str]) -> Line {
+        let indent = " ".repeat(self.selection_marker.chars().count());
+
+        Line::from_spans(
+            vec![
+                Span::new(format!("{}{}: ", indent, label), self.score),
+                Span::new(words.join(", "), self.description),
+            ],
+            0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_themes() -> Result<(), ThemeError> {
+        let theme = Theme::build(Some("no-color"), Some("$ ".to_string()), None, true)?;
+
+        assert_eq!(theme.selected, None);
+        assert_eq!(theme.prompt, "$ ");
+        assert_eq!(theme.normal_prompt, ": ");
+        assert!(theme.show_score);
+
+        assert_eq!(
+            Theme::build(Some("neon"), None, None, false).err().unwrap(),
+            ThemeError::UnknownTheme("neon".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_highlights_matched_prefix() {
+        let mut theme = Theme::high_contrast();
+        theme.show_score = true;
+
+        let line = theme.suggestion_line("hello", "he", 3, Some("greeting"), Some("team"), true);
+
+        assert_eq!(
+            line.spans,
+            vec![
+                Span::new("> ", Some(Color::White)),
+                Span::new("he", Some(Color::Yellow)),
+                Span::new("llo", Some(Color::White)),
+                Span::new(" (3)", Some(Color::Cyan)),
+                Span::new(" [team]", Some(Color::Cyan)),
+                Span::new("  greeting", Some(Color::Magenta)),
+            ]
+        );
+
+        let line = Theme::no_color().suggestion_line("hello", "he", 3, None, None, false);
+
+        assert_eq!(
+            line.spans,
+            vec![Span::new("  ", None), Span::new("hello", None)]
+        );
+
+        let line = Theme::high_contrast().related_line("synonyms", &["glad", "cheerful"]);
+
+        assert_eq!(
+            line.spans,
+            vec![
+                Span::new("  synonyms: ", Some(Color::Cyan)),
+                Span::new("glad, cheerful", Some(Color::Magenta)),
+            ]
+        );
+    }
+}